@@ -0,0 +1,25 @@
+use kg_g::simulation::{Simulation, FIXED_DELTA, HEADLESS_VIEWPORT};
+
+const SEED: u64 = 42;
+const ACTORS: [usize; 3] = [500, 1000, 3000];
+const FRAMES: u32 = 300;
+
+fn main() {
+  for actors in ACTORS {
+    let mut simulation = Simulation::new(HEADLESS_VIEWPORT, SEED).with_enemies(actors);
+    let start = std::time::Instant::now();
+    for _ in 0..FRAMES {
+      simulation.update(FIXED_DELTA);
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+      "actors: {}, frames: {}, total: {:.2?}, per frame: {:.2?}, actors left: {}",
+      actors,
+      FRAMES,
+      elapsed,
+      elapsed / FRAMES,
+      simulation.world.get_ai_actors().len()
+    );
+  }
+}
@@ -0,0 +1,60 @@
+use kg_g::{
+  simulation::{Simulation, FIXED_DELTA, HEADLESS_VIEWPORT},
+  systems::rng::generate_seed,
+  replay::{Replay, hash_tables},
+  world_module::{enemy::EnemyTable, pickup::DropTable},
+  director::WaveScript,
+};
+
+
+const HEADLESS_MAX_FRAMES: usize = 60 * 60 * 30;
+
+fn get_arg(name: &str) -> Option<String> {
+  let args: Vec<String> = std::env::args().collect();
+  args.iter()
+    .position(|arg| arg == name)
+    .and_then(|i| args.get(i + 1))
+    .cloned()
+}
+
+fn get_seed_arg() -> Option<u64> {
+  get_arg("--seed").and_then(|seed| seed.parse().ok())
+}
+
+// the same files the game loads, so replays recorded with edited tables play back the same
+fn load_asset(name: &str) -> String {
+  let path = format!("{}/assets/{}", env!("CARGO_MANIFEST_DIR"), name);
+  std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{} should be loaded: {}", path, e))
+}
+
+fn main() {
+  let enemies_ini = load_asset("enemies.ini");
+  let waves_ini = load_asset("waves.ini");
+  let drops_ini = load_asset("pickups.ini");
+  let tables_hash = hash_tables(&[&enemies_ini, &waves_ini, &drops_ini]);
+  let with_tables = |simulation: Simulation| simulation
+    .with_enemy_table(EnemyTable::parse(&enemies_ini).expect("enemies.ini should be valid"))
+    .with_wave_script(WaveScript::parse(&waves_ini).expect("waves.ini should be valid"))
+    .with_drop_table(DropTable::parse(&drops_ini).expect("pickups.ini should be valid"));
+
+  let (seed, simulation, frames) = if let Some(path) = get_arg("--replay") {
+    let replay = Replay::load(&path).expect("replay should be loaded");
+    replay.check_tables(tables_hash).expect("replay should match the tables in assets");
+    (replay.seed, replay.play_headless(with_tables(replay.create_simulation())), replay.frames.len())
+  } else {
+    let seed = get_seed_arg().unwrap_or_else(generate_seed);
+    let mut simulation = with_tables(Simulation::new(HEADLESS_VIEWPORT, seed));
+    let frames = simulation.run_headless(FIXED_DELTA, HEADLESS_MAX_FRAMES);
+    (seed, simulation, frames)
+  };
+
+  println!(
+    "seed: {}, frames: {}, time: {:.2}s, score: {}, difficulty: {}, game over: {}",
+    seed,
+    frames,
+    simulation.time,
+    simulation.world.score,
+    simulation.difficulty,
+    simulation.is_over()
+  );
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use macroquad::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct ConfigSection {
+  pub name: String,
+  entries: Vec<(String, String)>,
+}
+
+impl ConfigSection {
+  pub fn new(name: &str) -> Self {
+    Self { name: name.to_owned(), entries: vec![] }
+  }
+
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+  }
+
+  pub fn require<T: FromStr>(&self, key: &str) -> Result<T, String> {
+    let value = self.get(key).ok_or_else(|| format!("[{}] missing key: {}", self.name, key))?;
+    parse_value(&self.name, key, value)
+  }
+
+  pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+    match self.get(key) {
+      Some(value) => parse_value(&self.name, key, value),
+      None => Ok(default),
+    }
+  }
+
+  pub fn get_list_or<T: FromStr + Clone>(&self, key: &str, default: &[T]) -> Result<Vec<T>, String> {
+    match self.get(key) {
+      Some(value) => value.split_whitespace().map(|v| parse_value(&self.name, key, v)).collect(),
+      None => Ok(default.to_vec()),
+    }
+  }
+}
+
+fn parse_value<T: FromStr>(section: &str, key: &str, value: &str) -> Result<T, String> {
+  value.parse::<T>().map_err(|_| format!("[{}] invalid value for {}: {}", section, key, value))
+}
+
+pub fn parse_color(value: &str) -> Result<Color, String> {
+  let hex = value.trim_start_matches('#');
+  if hex.len() != 6 {
+    return Err(format!("invalid color: {}", value));
+  }
+  let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("invalid color: {}", value));
+  Ok(Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, 255))
+}
+
+pub fn parse_config(data: &str) -> Result<Vec<ConfigSection>, String> {
+  let mut sections: Vec<ConfigSection> = vec![];
+
+  for (line_number, line) in data.lines().enumerate() {
+    let line = line.split('#').next().unwrap_or("").trim();
+    if line.is_empty() {
+      continue;
+    }
+
+    if line.starts_with('[') && line.ends_with(']') {
+      sections.push(ConfigSection::new(line[1..line.len() - 1].trim()));
+      continue;
+    }
+
+    let (key, value) = line
+      .split_once('=')
+      .ok_or_else(|| format!("line {}: expected key = value", line_number + 1))?;
+    let section = sections
+      .last_mut()
+      .ok_or_else(|| format!("line {}: value outside of a section", line_number + 1))?;
+    section.entries.push((key.trim().to_owned(), value.trim().to_owned()));
+  }
+
+  Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_sections() {
+    let sections = parse_config("
+      # comment
+      [goblin]
+      speed = 70 # inline comment
+      ai_weights = 1 5 7 0
+
+      [brute]
+      hp = 5
+    ").expect("config should be parsed");
+
+    assert_eq!(sections.len(), 2);
+    assert_eq!(sections[0].name, "goblin");
+    assert_eq!(sections[0].require::<f32>("speed"), Ok(70.));
+    assert_eq!(sections[0].get_list_or::<i32>("ai_weights", &[]), Ok(vec![1, 5, 7, 0]));
+    assert_eq!(sections[1].get_or::<isize>("hp", 1), Ok(5));
+    assert_eq!(sections[1].get_or::<isize>("score", 1), Ok(1));
+    assert!(sections[1].require::<f32>("speed").is_err());
+  }
+
+  #[test]
+  fn parse_invalid() {
+    assert!(parse_config("speed = 70").is_err());
+    assert!(parse_config("[goblin]\nspeed").is_err());
+    assert_eq!(parse_color("#399f32"), Ok(Color::from_rgba(57, 159, 50, 255)));
+    assert!(parse_color("39f32").is_err());
+  }
+}
@@ -0,0 +1,461 @@
+use std::f32::consts::PI;
+
+use macroquad::prelude::*;
+
+use crate::{config::{parse_config, ConfigSection}, world_module::{world::World, enemy::EnemyTable, damage::Hp}, systems::{timer::Timer, rng::Rng}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
+
+pub const DEFAULT_WAVES: &str = include_str!("../assets/waves.ini");
+
+const DIRECTOR_SECTION: &str = "director";
+const DEFAULT_RING_RADIUS: f32 = 260.;
+const DEFAULT_CLUSTER_RADIUS: f32 = 48.;
+// same as the wandering margin in Ai::set_state, keeps spawns clear of the out of bounds check
+const SPAWN_MARGIN: f32 = 32.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnPattern {
+  Edge,
+  Ring,
+  Line,
+  Cluster,
+}
+
+impl SpawnPattern {
+  fn parse(value: &str) -> Result<Self, String> {
+    match value {
+      "edge" => Ok(SpawnPattern::Edge),
+      "ring" => Ok(SpawnPattern::Ring),
+      "line" => Ok(SpawnPattern::Line),
+      "cluster" => Ok(SpawnPattern::Cluster),
+      _ => Err(format!("unknown spawn pattern: {}", value)),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Wave {
+  pub name: String,
+  pub duration: f32,
+  pub difficulty: usize,
+  pub spawn_interval: f32,
+  pub spawn_count: usize,
+  pub pattern: SpawnPattern,
+  pub radius: f32,
+  pub enemies: Vec<(String, i32)>,
+  pub boss: Option<String>,
+  pub boss_count: usize,
+  pub boss_hp_multiplier: isize,
+}
+
+impl Wave {
+  fn from_section(section: &ConfigSection, previous_difficulty: usize) -> Result<Self, String> {
+    let pattern = SpawnPattern::parse(&section.get_or("pattern", "edge".to_owned())?)?;
+    let default_radius = if pattern == SpawnPattern::Cluster { DEFAULT_CLUSTER_RADIUS } else { DEFAULT_RING_RADIUS };
+
+    let enemies = section.get_list_or::<String>("enemies", &[])?
+      .iter()
+      .map(|entry| match entry.split_once(':') {
+        Some((name, weight)) => weight
+          .parse::<i32>()
+          .map(|weight| (name.to_owned(), weight))
+          .map_err(|_| format!("[{}] invalid enemy weight: {}", section.name, entry)),
+        None => Ok((entry.clone(), 1)),
+      })
+      .collect::<Result<Vec<(String, i32)>, String>>()?;
+
+    Ok(Self {
+      name: section.name.clone(),
+      duration: section.get_or("duration", 0.)?,
+      difficulty: section.get_or("difficulty", previous_difficulty)?,
+      spawn_interval: section.get_or("spawn_interval", 0.)?,
+      spawn_count: section.get_or("spawn_count", 1)?,
+      pattern,
+      radius: section.get_or("radius", default_radius)?,
+      enemies,
+      boss: section.get("boss").map(|boss| boss.to_owned()),
+      boss_count: section.get_or("boss_count", 1)?,
+      boss_hp_multiplier: section.get_or("boss_hp_multiplier", 1)?,
+    })
+  }
+
+  fn choose_enemy(&self, enemies: &EnemyTable, difficulty: usize, rng: &mut Rng) -> Option<usize> {
+    let total: i32 = self.enemies.iter().map(|(_, w)| w).sum();
+    if total <= 0 {
+      return enemies.choose(difficulty, rng);
+    }
+
+    let mut result = rng.gen_range::<i32>(0, total);
+    for (name, weight) in &self.enemies {
+      if result < *weight {
+        return enemies.find(name);
+      }
+      result -= weight;
+    }
+    None
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct WaveScript {
+  pub waves: Vec<Wave>,
+  pub loop_from: usize,
+  pub loop_difficulty: usize,
+}
+
+impl WaveScript {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let sections = parse_config(data)?;
+    let mut waves: Vec<Wave> = vec![];
+    for section in sections.iter().filter(|s| s.name != DIRECTOR_SECTION) {
+      let previous_difficulty = waves.last().map(|w| w.difficulty).unwrap_or(0);
+      waves.push(Wave::from_section(section, previous_difficulty)?);
+    }
+
+    if waves.is_empty() {
+      return Err("no waves defined".to_owned());
+    }
+    if waves.iter().all(|w| w.duration <= 0. && w.boss.is_none()) {
+      return Err("waves need a duration or a boss".to_owned());
+    }
+
+    let (loop_from, loop_difficulty) = match sections.iter().find(|s| s.name == DIRECTOR_SECTION) {
+      Some(section) => {
+        let loop_from = match section.get("loop_from") {
+          Some(name) => waves.iter().position(|w| w.name == name).ok_or_else(|| format!("unknown wave to loop from: {}", name))?,
+          None => 0,
+        };
+        (loop_from, section.get_or("loop_difficulty", 0)?)
+      },
+      None => (0, 0),
+    };
+
+    Ok(Self { waves, loop_from, loop_difficulty })
+  }
+
+  pub fn validate(&self, enemies: &EnemyTable) -> Result<(), String> {
+    for wave in &self.waves {
+      let names = wave.enemies.iter().map(|(name, _)| name).chain(wave.boss.iter());
+      for name in names {
+        if enemies.find(name).is_none() {
+          return Err(format!("[{}] unknown enemy: {}", wave.name, name));
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Default for WaveScript {
+  fn default() -> Self {
+    Self::parse(DEFAULT_WAVES).expect("assets/waves.ini should be valid")
+  }
+}
+
+fn get_spawn_area(world: &World, viewport: (f32, f32)) -> Rect {
+  let center = world.player.actor.movable.position;
+  let bounds = &world.bounds;
+  let left = (center.x - viewport.0 / 2.).max(bounds.left());
+  let right = (center.x + viewport.0 / 2.).min(bounds.right());
+  let top = (center.y - viewport.1 / 2.).max(bounds.top());
+  let bottom = (center.y + viewport.1 / 2.).min(bounds.bottom());
+  Rect::new(left, top, right - left, bottom - top)
+}
+
+fn get_edge_position(area: &Rect, rng: &mut Rng) -> Vec2 {
+  let positions = [
+    Vec2::new(area.left(), rng.gen_range::<f32>(area.top(), area.bottom())),
+    Vec2::new(area.right(), rng.gen_range::<f32>(area.top(), area.bottom())),
+    Vec2::new(rng.gen_range::<f32>(area.left(), area.right()), area.top()),
+    Vec2::new(rng.gen_range::<f32>(area.left(), area.right()), area.bottom()),
+  ];
+  *rng.choose(&positions).unwrap_or(&positions[0])
+}
+
+fn get_spawn_positions(wave: &Wave, count: usize, world: &mut World, viewport: (f32, f32)) -> Vec<Vec2> {
+  let area = get_spawn_area(world, viewport);
+  let center = world.player.actor.movable.position;
+  let rng = &mut world.rng;
+
+  let positions: Vec<Vec2> = match wave.pattern {
+    SpawnPattern::Edge => (0..count).map(|_| get_edge_position(&area, rng)).collect(),
+    SpawnPattern::Ring => {
+      let offset = rng.gen_range::<f32>(0., PI * 2.);
+      (0..count)
+        .map(|i| {
+          let angle = offset + PI * 2. * i as f32 / count as f32;
+          center + Vec2::new(angle.cos(), angle.sin()) * wave.radius
+        })
+        .collect()
+    },
+    SpawnPattern::Line => {
+      let (start, end) = match rng.gen_range::<usize>(0, 4) {
+        0 => (area.point(), Vec2::new(area.right(), area.top())),
+        1 => (Vec2::new(area.left(), area.bottom()), Vec2::new(area.right(), area.bottom())),
+        2 => (area.point(), Vec2::new(area.left(), area.bottom())),
+        _ => (Vec2::new(area.right(), area.top()), Vec2::new(area.right(), area.bottom())),
+      };
+      (0..count)
+        .map(|i| start.lerp(end, (i as f32 + 0.5) / count as f32))
+        .collect()
+    },
+    SpawnPattern::Cluster => {
+      let origin = get_edge_position(&area, rng);
+      (0..count)
+        .map(|_| origin + Vec2::new(rng.gen_range::<f32>(-wave.radius, wave.radius), rng.gen_range::<f32>(-wave.radius, wave.radius)))
+        .collect()
+    },
+  };
+
+  positions
+    .into_iter()
+    .map(|p| world.clamp_to_bounds(p, Vec2::splat(SPAWN_MARGIN)))
+    .collect()
+}
+
+pub struct SpawnDirector {
+  wave: usize,
+  loops: usize,
+  wave_time: f32,
+  spawn_timer: Timer,
+  bosses: Vec<usize>,
+  started: bool,
+}
+
+impl SpawnDirector {
+  pub fn new() -> Self {
+    Self {
+      wave: 0,
+      loops: 0,
+      wave_time: 0.,
+      spawn_timer: Timer::new(0.),
+      bosses: vec![],
+      started: false,
+    }
+  }
+
+  pub fn get_wave<'a>(&self, script: &'a WaveScript) -> &'a Wave {
+    &script.waves[self.wave.min(script.waves.len() - 1)]
+  }
+
+  pub fn get_difficulty(&self, script: &WaveScript) -> usize {
+    self.get_wave(script).difficulty + self.loops * script.loop_difficulty
+  }
+
+  fn is_wave_finished(&self, wave: &Wave, world: &World) -> bool {
+    self.wave_time >= wave.duration && self.bosses.iter().all(|id| !world.has_ai_actor(*id))
+  }
+
+  fn next_wave(&mut self, script: &WaveScript) {
+    self.wave += 1;
+    if self.wave >= script.waves.len() {
+      self.wave = script.loop_from;
+      self.loops += 1;
+    }
+    self.started = false;
+  }
+
+  fn start_wave(&mut self, script: &WaveScript, enemies: &EnemyTable, world: &mut World, viewport: (f32, f32)) {
+    let wave = self.get_wave(script);
+    let difficulty = self.get_difficulty(script);
+
+    self.wave_time = 0.;
+    self.spawn_timer.set_treshold(wave.spawn_interval);
+    self.spawn_timer.reset();
+    self.bosses.clear();
+    self.started = true;
+
+    let boss = wave.boss.as_ref().and_then(|name| enemies.find(name));
+    if let Some(kind) = boss {
+      for position in get_spawn_positions(wave, wave.boss_count, world, viewport) {
+        let (mut actor, ai) = enemies.spawn(kind, position, difficulty, &mut world.rng);
+        actor.hp = Hp::new(actor.hp.act_hp * wave.boss_hp_multiplier);
+        actor.score_value *= wave.boss_hp_multiplier.max(1) as usize;
+        actor.xp_value *= wave.boss_hp_multiplier.max(1) as usize;
+        self.bosses.push(actor.get_id());
+        world.add_ai_actor(actor, ai);
+      }
+    }
+  }
+
+  fn spawn(&mut self, script: &WaveScript, enemies: &EnemyTable, world: &mut World, viewport: (f32, f32)) {
+    let wave = self.get_wave(script);
+    let difficulty = self.get_difficulty(script);
+
+    for position in get_spawn_positions(wave, wave.spawn_count, world, viewport) {
+      if let Some(kind) = wave.choose_enemy(enemies, difficulty, &mut world.rng) {
+        let (actor, ai) = enemies.spawn(kind, position, difficulty, &mut world.rng);
+        world.add_ai_actor(actor, ai);
+      }
+    }
+  }
+
+  pub fn update(&mut self, delta_t: f32, script: &WaveScript, enemies: &EnemyTable, world: &mut World, viewport: (f32, f32)) {
+    if !self.started {
+      self.start_wave(script, enemies, world, viewport);
+    }
+
+    self.wave_time += delta_t;
+    if self.get_wave(script).spawn_interval > 0. {
+      self.spawn_timer.update(delta_t);
+      if self.spawn_timer.is_just_over() {
+        self.spawn(script, enemies, world, viewport);
+      }
+    }
+
+    if self.is_wave_finished(self.get_wave(script), world) {
+      self.next_wave(script);
+    }
+  }
+}
+
+impl Snapshot for SpawnDirector {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.wave);
+    writer.write(self.loops);
+    writer.write(self.wave_time);
+    writer.write_snapshot(&self.spawn_timer);
+    writer.write(self.bosses.len());
+    for id in &self.bosses {
+      writer.write(id);
+    }
+    writer.write_bool(self.started);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let wave = reader.read()?;
+    let loops = reader.read()?;
+    let wave_time = reader.read()?;
+    let spawn_timer = reader.read_snapshot()?;
+    let count: usize = reader.read()?;
+    let bosses = (0..count).map(|_| reader.read()).collect::<Result<Vec<usize>, String>>()?;
+
+    Ok(Self {
+      wave,
+      loops,
+      wave_time,
+      spawn_timer,
+      bosses,
+      started: reader.read_bool()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{player::Player, world_module::actor::Actor};
+
+  const SCRIPT: &str = "
+    [director]
+    loop_from = swarm
+    loop_difficulty = 3
+
+    [warmup]
+    duration = 1
+    spawn_interval = 0.4
+    spawn_count = 2
+    enemies = goblin
+
+    [rest]
+    duration = 1
+
+    [boss]
+    difficulty = 2
+    boss = brute
+    boss_hp_multiplier = 4
+
+    [swarm]
+    duration = 1
+    spawn_interval = 0.5
+    spawn_count = 5
+    pattern = ring
+  ";
+
+  fn create_world() -> World {
+    World::new(Player::new(Actor::new(Vec2::ZERO, 100., 5)), Rng::new(5))
+  }
+
+  fn run(director: &mut SpawnDirector, script: &WaveScript, world: &mut World, seconds: f32) {
+    let enemies = EnemyTable::default();
+    for _ in 0..(seconds * 60.) as usize {
+      director.update(1. / 60., script, &enemies, world, (640., 480.));
+    }
+  }
+
+  #[test]
+  fn parse_waves() {
+    let script = WaveScript::parse(SCRIPT).expect("script should be parsed");
+
+    assert_eq!(script.waves.len(), 4);
+    assert_eq!(script.loop_from, 3);
+    assert_eq!(script.waves[1].spawn_interval, 0.);
+    assert_eq!(script.waves[1].difficulty, 0);
+    assert_eq!(script.waves[3].difficulty, 2);
+    assert_eq!(script.waves[3].pattern, SpawnPattern::Ring);
+    assert!(script.validate(&EnemyTable::default()).is_ok());
+    assert!(WaveScript::default().validate(&EnemyTable::default()).is_ok());
+    assert!(WaveScript::parse("[a]\nduration = 1\nspawn_interval = 1\nenemies = dragon").expect("script should be parsed").validate(&EnemyTable::default()).is_err());
+    assert!(WaveScript::parse("[a]\npattern = spiral").is_err());
+  }
+
+  #[test]
+  fn waves_progress_and_wait_for_boss() {
+    let script = WaveScript::parse(SCRIPT).expect("script should be parsed");
+    let mut director = SpawnDirector::new();
+    let mut world = create_world();
+
+    run(&mut director, &script, &mut world, 1.1);
+    assert_eq!(director.get_wave(&script).name, "rest");
+    assert_eq!(world.get_ai_actors().len(), 4);
+
+    run(&mut director, &script, &mut world, 1.);
+    assert_eq!(director.get_wave(&script).name, "boss");
+    run(&mut director, &script, &mut world, 5.);
+    assert_eq!(director.get_wave(&script).name, "boss");
+    assert_eq!(world.get_ai_actors().len(), 5);
+    assert_eq!(world.get_ai_actors()[4].hp.act_hp, 24);
+  }
+
+  #[test]
+  fn ring_surrounds_player() {
+    let script = WaveScript::parse(SCRIPT).expect("script should be parsed");
+    let mut world = create_world();
+    let positions = get_spawn_positions(&script.waves[3], 4, &mut world, (640., 480.));
+
+    assert_eq!(positions.len(), 4);
+    for position in positions {
+      assert!((position.length() - DEFAULT_RING_RADIUS).abs() < 0.01);
+    }
+  }
+
+  #[test]
+  fn spawns_stay_inside_bounds() {
+    let mut world = create_world();
+    for corner in [world.bounds.point(), world.bounds.point() + world.bounds.size() - Vec2::ONE] {
+      world.player.actor.movable.position = corner;
+      for pattern in ["edge", "ring", "line", "cluster"] {
+        let script = WaveScript::parse(&format!("[a]\nduration = 1\npattern = {}", pattern)).expect("script should be parsed");
+        for position in get_spawn_positions(&script.waves[0], 20, &mut world, (640., 480.)) {
+          assert!(world.bounds.contains(position), "{} spawn {} is out of bounds", pattern, position);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn loops_increase_difficulty() {
+    let script = WaveScript::parse("
+      [director]
+      loop_difficulty = 3
+
+      [only]
+      duration = 1
+      difficulty = 1
+    ").expect("script should be parsed");
+    let mut director = SpawnDirector::new();
+    let mut world = create_world();
+
+    run(&mut director, &script, &mut world, 2.5);
+    assert_eq!(director.get_difficulty(&script), 7);
+  }
+}
@@ -2,7 +2,25 @@ use std::f32::consts::PI;
 
 use macroquad::prelude::*;
 
-use crate::{world_module::{actor::Actor, projectile::Projectile, movable::Movable, particle::Particle, world::World}, systems::{cd::CdBounds}};
+use crate::{world_module::{actor::Actor, projectile::Projectile, movable::Movable, particle::Particle, world::World, pickup::{Pickup, PickupKind}}, systems::{cd::{CdBounds, CdShape, LAYER_ENEMY_PROJECTILE}}, stage_module::resources::{Resources, Palette}, highscore::HighScoreTable};
+
+pub fn draw_high_scores(left: f32, top: f32, high_scores: &HighScoreTable, highlight: Option<usize>) {
+  let font_size: f32 = 14.;
+  draw_text("HIGH SCORES", left, top, font_size, WHITE);
+  if high_scores.entries.is_empty() {
+    draw_text("no runs yet", left, top + font_size, font_size, GRAY);
+  }
+  for (rank, entry) in high_scores.entries.iter().enumerate() {
+    let color = if highlight == Some(rank) { YELLOW } else { WHITE };
+    draw_text(
+      format!("{:>2}. {:>6}  lvl {:>2}  {:>6.1}s  {}  seed {}", rank + 1, entry.score, entry.difficulty, entry.time, entry.date, entry.seed).as_str(),
+      left,
+      top + font_size * (rank + 1) as f32,
+      font_size,
+      color
+    );
+  }
+}
 
 pub struct Renderer {
   pub debug: bool
@@ -10,14 +28,20 @@ pub struct Renderer {
 
 impl Renderer {
 
-  pub fn draw_player_info(&self, left: f32, top: f32, world: &World, difficulty: usize) {
+  pub fn draw_player_info(&self, left: f32, top: f32, world: &World, difficulty: usize, wave: &str) {
     draw_text(
-      format!("HP: {}, SCORE: {}, LVL: {}", world.player.actor.hp.act_hp, world.score, difficulty).as_str(),
+      format!("HP: {}, SCORE: {}, LVL: {}, WAVE: {}", world.player.actor.hp.act_hp, world.score, difficulty, wave).as_str(),
       left + 4.,
       top + 40.,
       24.,
       WHITE
     );
+
+    let player = world.get_player();
+    let progress = player.xp as f32 / player.get_xp_to_next_level() as f32;
+    draw_rectangle(left + 4., top + 48., 200., 8., Color::from_rgba(40, 40, 40, 200));
+    draw_rectangle(left + 4., top + 48., 200. * progress.min(1.), 8., SKYBLUE);
+    draw_text(format!("LV {}", player.level).as_str(), left + 210., top + 56., 16., WHITE);
   }
 
   pub fn draw_debug(&self, left: f32, top: f32, world: &World) {
@@ -32,13 +56,31 @@ impl Renderer {
       draw_text(format!("ai actors count: {}", world.get_ai_actors().len()).as_str(), o_x, o_y + font_size * 2., font_size, WHITE);
       draw_text(format!("projecties count: {}", world.get_projectiles().len()).as_str(), o_x, o_y + font_size * 3., font_size, WHITE);
       draw_text(format!("particles count: {}", world.get_particles().len()).as_str(), o_x, o_y + font_size * 4., font_size, WHITE);
-      draw_text(format!("fire ready: {}", world.get_player().projectile_timer.is_over()).as_str(), o_x, o_y + font_size * 5., font_size, WHITE);
+      let weapons: Vec<&str> = world.get_player().weapons.iter().map(|w| w.kind.get_name()).collect();
+      draw_text(format!("weapons: {}", weapons.join(", ")).as_str(), o_x, o_y + font_size * 5., font_size, WHITE);
+      draw_text(format!("pickups count: {}", world.get_pickups().len()).as_str(), o_x, o_y + font_size * 6., font_size, WHITE);
     }
   }
 
-  fn draw_cd_data(bounding_rect: &CdBounds) {
-    let rect = bounding_rect.get_rect();
-    draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::from_rgba(124, 255, 124, 124));
+  fn draw_cd_data(bounds: &CdBounds) {
+    let color = Color::from_rgba(124, 255, 124, 124);
+    let position = bounds.get_position();
+    match bounds.get_shape() {
+      CdShape::Aabb { .. } => {
+        let rect = bounds.get_rect();
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+      },
+      CdShape::Circle { radius } => {
+        draw_circle(position.x, position.y, radius, color);
+      },
+      CdShape::Capsule { radius, .. } => {
+        if let Some((a, b)) = bounds.get_segment() {
+          draw_line(a.x, a.y, b.x, b.y, radius * 2., color);
+          draw_circle(a.x, a.y, radius, color);
+          draw_circle(b.x, b.y, radius, color);
+        }
+      },
+    }
   }
 
   fn draw_movable_data(movable: &Movable, o_x: f32, o_y: f32) {
@@ -99,12 +141,19 @@ impl Renderer {
     }
   }
 
+  fn draw_actor_with_palette(&self, resources: &Resources, palette: Palette, actor: &Actor) {
+    let texture = resources.use_palette(palette);
+    self.draw_actor(&texture, actor);
+    resources.reset_palette();
+  }
+
   pub fn draw_projectile(&self, texture: &Texture2D, projectile: &Projectile) {
+    let color = if projectile.cd_bounds.get_layer() == LAYER_ENEMY_PROJECTILE { LIME } else { WHITE };
     draw_texture_ex(
       *texture,
       projectile.movable.position.x - 16.,
       projectile.movable.position.y - 16.,
-      WHITE,
+      color,
       DrawTextureParams {
           dest_size: Some(vec2(32., 32.)),
           source: Some(projectile.get_source()),
@@ -119,6 +168,30 @@ impl Renderer {
     }
   }
 
+  pub fn draw_pickup(&self, pickup: &Pickup) {
+    if pickup.is_expiring() && (get_time() * 8.) as i32 % 2 == 0 {
+      return;
+    }
+
+    let Vec2 { x, y } = pickup.movable.position;
+    match pickup.kind {
+      PickupKind::Health => {
+        draw_rectangle(x - 6., y - 2., 12., 4., RED);
+        draw_rectangle(x - 2., y - 6., 4., 12., RED);
+      },
+      PickupKind::Experience => draw_poly(x, y, 4, 6., 0., SKYBLUE),
+      PickupKind::Magnet => draw_circle_lines(x, y, 7., 3., VIOLET),
+      PickupKind::Bomb => {
+        draw_circle(x, y, 7., BLACK);
+        draw_line(x + 4., y - 4., x + 8., y - 9., 2., ORANGE);
+      },
+    }
+
+    if self.debug {
+      Renderer::draw_cd_data(&pickup.cd_bounds);
+    }
+  }
+
   pub fn draw_particle(&self, texture: &Texture2D, particle: &Particle) {
     draw_texture_ex(
       *texture,
@@ -134,4 +207,29 @@ impl Renderer {
     );
   }
 
+  pub fn draw_world(&self, resources: &Resources, world: &World) {
+    draw_rectangle(world.bounds.x, world.bounds.x, world.bounds.w, world.bounds.h, DARKGRAY);
+
+    for pickup in world.get_pickups() {
+      self.draw_pickup(pickup);
+    }
+
+    for actor in world.get_dying_actors() {
+      self.draw_actor_with_palette(resources, Palette::Enemy(actor.kind), actor);
+    }
+
+    let player = &world.get_player().actor;
+    let player_palette = if world.player.invlunerable || player.is_hurt() { Palette::Flash } else { Palette::Player };
+    self.draw_actor_with_palette(resources, player_palette, player);
+    for actor in world.get_ai_actors() {
+      let palette = if actor.is_hurt() { Palette::Flash } else { Palette::Enemy(actor.kind) };
+      self.draw_actor_with_palette(resources, palette, actor);
+    }
+    for projectile in world.get_projectiles() {
+      self.draw_projectile(&resources.texture_sheet, projectile);
+    }
+    for particle in world.get_particles() {
+      self.draw_particle(&resources.texture_sheet, particle);
+    }
+  }
 }
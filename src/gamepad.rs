@@ -0,0 +1,145 @@
+use macroquad::prelude::*;
+
+const STICK_DEAD_ZONE: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+  South,
+  East,
+  West,
+  North,
+  LeftBumper,
+  RightBumper,
+  LeftTrigger,
+  RightTrigger,
+  Select,
+  Start,
+  DPadUp,
+  DPadDown,
+  DPadLeft,
+  DPadRight,
+}
+
+pub const GAMEPAD_BUTTONS: [GamepadButton; 14] = [
+  GamepadButton::South, GamepadButton::East, GamepadButton::West, GamepadButton::North,
+  GamepadButton::LeftBumper, GamepadButton::RightBumper, GamepadButton::LeftTrigger, GamepadButton::RightTrigger,
+  GamepadButton::Select, GamepadButton::Start,
+  GamepadButton::DPadUp, GamepadButton::DPadDown, GamepadButton::DPadLeft, GamepadButton::DPadRight,
+];
+
+// macroquad 0.3 has no gamepad support, desktop reads gamepads through gilrs, polled once per frame
+// from the main loop, wasm and Android have no gamepad backend.
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+mod backend {
+  use std::cell::RefCell;
+
+  use gilrs::{Axis, Button, Gilrs};
+  use macroquad::prelude::*;
+
+  use super::GamepadButton;
+
+  thread_local! {
+    static GILRS: RefCell<Option<Gilrs>> = RefCell::new(
+      Gilrs::new().map_err(|e| warn!("gamepads are not available: {}", e)).ok()
+    );
+  }
+
+  fn get_gilrs_button(button: GamepadButton) -> Button {
+    match button {
+      GamepadButton::South => Button::South,
+      GamepadButton::East => Button::East,
+      GamepadButton::West => Button::West,
+      GamepadButton::North => Button::North,
+      GamepadButton::LeftBumper => Button::LeftTrigger,
+      GamepadButton::RightBumper => Button::RightTrigger,
+      GamepadButton::LeftTrigger => Button::LeftTrigger2,
+      GamepadButton::RightTrigger => Button::RightTrigger2,
+      GamepadButton::Select => Button::Select,
+      GamepadButton::Start => Button::Start,
+      GamepadButton::DPadUp => Button::DPadUp,
+      GamepadButton::DPadDown => Button::DPadDown,
+      GamepadButton::DPadLeft => Button::DPadLeft,
+      GamepadButton::DPadRight => Button::DPadRight,
+    }
+  }
+
+  // Events are stamped with the frame counter, so buttons pressed this frame carry the current one.
+  pub fn update() {
+    GILRS.with(|gilrs| {
+      if let Some(gilrs) = gilrs.borrow_mut().as_mut() {
+        gilrs.inc();
+        while gilrs.next_event().is_some() {}
+      }
+    });
+  }
+
+  pub fn get_left_stick() -> Vec2 {
+    GILRS.with(|gilrs| {
+      gilrs.borrow().as_ref()
+        .and_then(|gilrs| gilrs.gamepads().next().map(|(_, gamepad)| Vec2::new(gamepad.value(Axis::LeftStickX), -gamepad.value(Axis::LeftStickY))))
+        .unwrap_or(Vec2::ZERO)
+    })
+  }
+
+  pub fn is_button_down(button: GamepadButton) -> bool {
+    GILRS.with(|gilrs| {
+      gilrs.borrow().as_ref()
+        .map(|gilrs| gilrs.gamepads().any(|(_, gamepad)| gamepad.is_pressed(get_gilrs_button(button))))
+        .unwrap_or(false)
+    })
+  }
+
+  pub fn is_button_pressed(button: GamepadButton) -> bool {
+    GILRS.with(|gilrs| {
+      gilrs.borrow().as_ref()
+        .map(|gilrs| gilrs.gamepads().any(|(_, gamepad)| {
+          gamepad.button_data(get_gilrs_button(button)).is_some_and(|data| data.is_pressed() && data.counter() == gilrs.counter())
+        }))
+        .unwrap_or(false)
+    })
+  }
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+mod backend {
+  use macroquad::prelude::*;
+
+  use super::GamepadButton;
+
+  pub fn update() {}
+
+  pub fn get_left_stick() -> Vec2 {
+    Vec2::ZERO
+  }
+
+  pub fn is_button_down(_button: GamepadButton) -> bool {
+    false
+  }
+
+  pub fn is_button_pressed(_button: GamepadButton) -> bool {
+    false
+  }
+}
+
+pub use backend::{update, is_button_down, is_button_pressed};
+
+pub fn parse_button(name: &str) -> Result<GamepadButton, String> {
+  GAMEPAD_BUTTONS
+    .iter()
+    .find(|button| format!("{:?}", button).eq_ignore_ascii_case(name))
+    .copied()
+    .ok_or_else(|| format!("unknown gamepad button: {}", name))
+}
+
+pub fn get_pressed_button() -> Option<GamepadButton> {
+  GAMEPAD_BUTTONS.iter().find(|button| is_button_pressed(**button)).copied()
+}
+
+// Left stick of the first connected gamepad, screen oriented (y down), zero inside the dead zone.
+pub fn get_stick_direction() -> Vec2 {
+  let stick = backend::get_left_stick().clamp_length_max(1.);
+  if stick.length() < STICK_DEAD_ZONE {
+    return Vec2::ZERO;
+  }
+  stick
+}
@@ -0,0 +1,133 @@
+use macroquad::miniquad::date;
+
+use crate::{config::parse_config, simulation::Simulation, storage};
+
+pub const HIGH_SCORES_PATH: &str = "highscores.ini";
+pub const MAX_HIGH_SCORES: usize = 10;
+
+const SECONDS_PER_DAY: f64 = 60. * 60. * 24.;
+
+// Days since 1970-01-01 to a (year, month, day) civil date, see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+pub fn format_date(seconds: f64) -> String {
+  let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY).floor() as i64);
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighScore {
+  pub score: usize,
+  pub difficulty: usize,
+  pub time: f32,
+  pub date: String,
+  pub seed: u64,
+}
+
+impl HighScore {
+  pub fn from_simulation(simulation: &Simulation) -> Self {
+    Self {
+      score: simulation.world.score,
+      difficulty: simulation.difficulty,
+      time: simulation.time,
+      date: format_date(date::now()),
+      seed: simulation.get_seed(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HighScoreTable {
+  pub entries: Vec<HighScore>,
+}
+
+impl HighScoreTable {
+  pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+    let rank = self.entries.iter().position(|e| entry.score > e.score).unwrap_or(self.entries.len());
+    if rank >= MAX_HIGH_SCORES {
+      return None;
+    }
+    self.entries.insert(rank, entry);
+    self.entries.truncate(MAX_HIGH_SCORES);
+    Some(rank)
+  }
+
+  pub fn serialize(&self) -> String {
+    self.entries
+      .iter()
+      .map(|e| format!(
+        "[entry]\nscore = {}\ndifficulty = {}\ntime = {:.2}\ndate = {}\nseed = {}",
+        e.score, e.difficulty, e.time, e.date, e.seed
+      ))
+      .collect::<Vec<String>>()
+      .join("\n\n")
+  }
+
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut table = Self::default();
+    for section in parse_config(data)?.iter().filter(|s| s.name == "entry") {
+      table.insert(HighScore {
+        score: section.require("score")?,
+        difficulty: section.get_or("difficulty", 0)?,
+        time: section.get_or("time", 0.)?,
+        date: section.get_or("date", "-".to_owned())?,
+        seed: section.get_or("seed", 0)?,
+      });
+    }
+    Ok(table)
+  }
+
+  pub fn load_or_default() -> Self {
+    Self::load(HIGH_SCORES_PATH).unwrap_or_default()
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), String> {
+    storage::write_string(path, &self.serialize())
+  }
+
+  pub fn load(path: &str) -> Result<Self, String> {
+    storage::read_string(path).and_then(|data| Self::parse(&data))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(score: usize) -> HighScore {
+    HighScore { score, difficulty: 2, time: 31.5, date: "2024-02-29".to_owned(), seed: 42 }
+  }
+
+  #[test]
+  fn insert_keeps_top_scores() {
+    let mut table = HighScoreTable::default();
+    for score in 1..=MAX_HIGH_SCORES {
+      assert!(table.insert(entry(score * 10)).is_some());
+    }
+
+    assert_eq!(table.insert(entry(5)), None);
+    assert_eq!(table.insert(entry(1000)), Some(0));
+    assert_eq!(table.insert(entry(55)), Some(6));
+    assert_eq!(table.entries.len(), MAX_HIGH_SCORES);
+    assert_eq!(table.entries.last().map(|e| e.score), Some(30));
+    assert_eq!(HighScoreTable::parse(&table.serialize()), Ok(table));
+  }
+
+  #[test]
+  fn format_dates() {
+    assert_eq!(format_date(0.), "1970-01-01");
+    assert_eq!(format_date(951782400.), "2000-02-29");
+    assert_eq!(format_date(1709251199.), "2024-02-29");
+  }
+}
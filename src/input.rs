@@ -0,0 +1,386 @@
+use macroquad::prelude::*;
+
+use crate::{config::parse_config, storage, gamepad::{self, GamepadButton}};
+
+pub const BINDINGS_PATH: &str = "controls.ini";
+
+const JOYSTICK_RADIUS: f32 = 60.;
+const JOYSTICK_DEAD_ZONE: f32 = 0.15;
+
+// Cancels rebinding in the controls screen, so it is never captured as a new trigger.
+pub const CANCEL_KEY: KeyCode = KeyCode::Escape;
+
+// Keys that can be used in bindings, referenced by their `KeyCode` name (e.g. `W`, `Up`, `F3`, `Key1`).
+const BINDABLE_KEYS: [KeyCode; 58] = [
+  KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I,
+  KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+  KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+  KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+  KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+  KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+  KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+  KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+  KeyCode::Space, KeyCode::Enter, KeyCode::Escape, KeyCode::Tab, KeyCode::LeftShift, KeyCode::Backspace,
+];
+
+pub fn parse_key(name: &str) -> Result<KeyCode, String> {
+  BINDABLE_KEYS
+    .iter()
+    .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+    .copied()
+    .ok_or_else(|| format!("unknown key: {}", name))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+  MoveTo(Vec2),
+  Steer(Vec2),
+  ChooseUpgrade(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+  MoveUp,
+  MoveDown,
+  MoveLeft,
+  MoveRight,
+  MoveTo,
+  Pause,
+  ToggleDebug,
+  Back,
+  Confirm,
+  Step,
+  Choice1,
+  Choice2,
+  Choice3,
+}
+
+pub const ACTIONS: [Action; 13] = [
+  Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight, Action::MoveTo,
+  Action::Pause, Action::ToggleDebug, Action::Back, Action::Confirm, Action::Step,
+  Action::Choice1, Action::Choice2, Action::Choice3,
+];
+
+impl Action {
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      Action::MoveUp => "move_up",
+      Action::MoveDown => "move_down",
+      Action::MoveLeft => "move_left",
+      Action::MoveRight => "move_right",
+      Action::MoveTo => "move_to",
+      Action::Pause => "pause",
+      Action::ToggleDebug => "toggle_debug",
+      Action::Back => "back",
+      Action::Confirm => "confirm",
+      Action::Step => "step",
+      Action::Choice1 => "choice_1",
+      Action::Choice2 => "choice_2",
+      Action::Choice3 => "choice_3",
+    }
+  }
+
+  fn get_default_triggers(&self) -> Vec<Trigger> {
+    use Trigger::{Key, Gamepad};
+    match self {
+      Action::MoveUp => vec![Key(KeyCode::W), Key(KeyCode::Up), Gamepad(GamepadButton::DPadUp)],
+      Action::MoveDown => vec![Key(KeyCode::S), Key(KeyCode::Down), Gamepad(GamepadButton::DPadDown)],
+      Action::MoveLeft => vec![Key(KeyCode::A), Key(KeyCode::Left), Gamepad(GamepadButton::DPadLeft)],
+      Action::MoveRight => vec![Key(KeyCode::D), Key(KeyCode::Right), Gamepad(GamepadButton::DPadRight)],
+      Action::MoveTo => vec![Trigger::Mouse(MouseButton::Left), Trigger::Touch],
+      Action::Pause => vec![Key(KeyCode::P), Gamepad(GamepadButton::Start)],
+      Action::ToggleDebug => vec![Key(KeyCode::F3)],
+      Action::Back => vec![Key(KeyCode::Escape), Gamepad(GamepadButton::East)],
+      Action::Confirm => vec![Key(KeyCode::Enter), Key(KeyCode::Space), Gamepad(GamepadButton::South)],
+      Action::Step => vec![Key(KeyCode::N)],
+      Action::Choice1 => vec![Key(KeyCode::Key1), Gamepad(GamepadButton::West)],
+      Action::Choice2 => vec![Key(KeyCode::Key2), Gamepad(GamepadButton::North)],
+      Action::Choice3 => vec![Key(KeyCode::Key3), Gamepad(GamepadButton::RightBumper)],
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+  Key(KeyCode),
+  Mouse(MouseButton),
+  Touch,
+  Gamepad(GamepadButton),
+}
+
+impl Trigger {
+  pub fn parse(name: &str) -> Result<Self, String> {
+    match name.to_ascii_lowercase().as_str() {
+      "mouseleft" => Ok(Trigger::Mouse(MouseButton::Left)),
+      "mouseright" => Ok(Trigger::Mouse(MouseButton::Right)),
+      "mousemiddle" => Ok(Trigger::Mouse(MouseButton::Middle)),
+      "touch" => Ok(Trigger::Touch),
+      name if name.starts_with("pad") => gamepad::parse_button(&name[3..]).map(Trigger::Gamepad),
+      _ => parse_key(name).map(Trigger::Key),
+    }
+  }
+
+  pub fn get_name(&self) -> String {
+    match self {
+      Trigger::Key(key) => format!("{:?}", key),
+      Trigger::Mouse(button) => format!("Mouse{:?}", button),
+      Trigger::Touch => "Touch".to_owned(),
+      Trigger::Gamepad(button) => format!("Pad{:?}", button),
+    }
+  }
+
+  fn is_pressed(&self) -> bool {
+    match self {
+      Trigger::Key(key) => is_key_pressed(*key),
+      Trigger::Mouse(button) => touches().is_empty() && is_mouse_button_pressed(*button),
+      Trigger::Touch => touches().iter().any(|t| t.phase == TouchPhase::Started),
+      Trigger::Gamepad(button) => gamepad::is_button_pressed(*button),
+    }
+  }
+
+  fn is_down(&self) -> bool {
+    match self {
+      Trigger::Key(key) => is_key_down(*key),
+      Trigger::Mouse(button) => touches().is_empty() && is_mouse_button_down(*button),
+      Trigger::Touch => !touches().is_empty(),
+      Trigger::Gamepad(button) => gamepad::is_button_down(*button),
+    }
+  }
+
+  // Returns the first trigger pressed this frame, used when rebinding an action, `CANCEL_KEY` is skipped.
+  pub fn get_pressed() -> Option<Self> {
+    if let Some(key) = BINDABLE_KEYS.iter().find(|key| **key != CANCEL_KEY && is_key_pressed(**key)) {
+      return Some(Trigger::Key(*key));
+    }
+    if let Some(button) = gamepad::get_pressed_button() {
+      return Some(Trigger::Gamepad(button));
+    }
+    if touches().iter().any(|t| t.phase == TouchPhase::Started) {
+      return Some(Trigger::Touch);
+    }
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+      .iter()
+      .find(|button| is_mouse_button_pressed(**button))
+      .map(|button| Trigger::Mouse(*button))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerPress {
+  pub position: Vec2,
+  pub touch_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+  triggers: Vec<Vec<Trigger>>,
+}
+
+impl Default for Bindings {
+  fn default() -> Self {
+    Self { triggers: ACTIONS.iter().map(|a| a.get_default_triggers()).collect() }
+  }
+}
+
+impl Bindings {
+  pub fn serialize(&self) -> String {
+    let mut lines = vec!["[controls]".to_owned()];
+    for action in ACTIONS {
+      let names: Vec<String> = self.get(action).iter().map(|t| t.get_name()).collect();
+      lines.push(format!("{} = {}", action.get_name(), names.join(" ")));
+    }
+    lines.join("\n")
+  }
+
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut bindings = Self::default();
+    if let Some(section) = parse_config(data)?.iter().find(|s| s.name == "controls") {
+      for action in ACTIONS {
+        if let Some(value) = section.get(action.get_name()) {
+          let triggers = value.split_whitespace().map(Trigger::parse).collect::<Result<Vec<Trigger>, String>>()?;
+          bindings.set(action, triggers);
+        }
+      }
+    }
+    Ok(bindings)
+  }
+
+  pub fn load_or_default() -> Self {
+    Self::load(BINDINGS_PATH).unwrap_or_default()
+  }
+
+  // Writes the default bindings on first start so there is a file to edit.
+  pub fn load_or_create() -> Self {
+    match storage::read_string(BINDINGS_PATH) {
+      Ok(data) => Self::parse(&data).unwrap_or_else(|e| {
+        warn!("{} is invalid, using default controls: {}", BINDINGS_PATH, e);
+        Self::default()
+      }),
+      Err(_) => {
+        let bindings = Self::default();
+        if let Err(e) = bindings.save(BINDINGS_PATH) {
+          warn!("controls could not be saved: {}", e);
+        }
+        bindings
+      },
+    }
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), String> {
+    storage::write_string(path, &self.serialize())
+  }
+
+  pub fn load(path: &str) -> Result<Self, String> {
+    storage::read_string(path).and_then(|data| Self::parse(&data))
+  }
+
+  pub fn get(&self, action: Action) -> &Vec<Trigger> {
+    &self.triggers[action as usize]
+  }
+
+  pub fn set(&mut self, action: Action, triggers: Vec<Trigger>) {
+    self.triggers[action as usize] = triggers;
+  }
+
+  pub fn add(&mut self, action: Action, trigger: Trigger) {
+    let triggers = &mut self.triggers[action as usize];
+    if !triggers.contains(&trigger) {
+      triggers.push(trigger);
+    }
+  }
+
+  pub fn is_pressed(&self, action: Action) -> bool {
+    self.get(action).iter().any(|t| t.is_pressed())
+  }
+
+  pub fn is_down(&self, action: Action) -> bool {
+    self.get(action).iter().any(|t| t.is_down())
+  }
+
+  pub fn get_pointer_presses(&self, action: Action) -> Vec<PointerPress> {
+    let mut presses = vec![];
+    for trigger in self.get(action) {
+      match trigger {
+        Trigger::Mouse(_) if trigger.is_pressed() => presses.push(PointerPress { position: Vec2::from(mouse_position()), touch_id: None }),
+        Trigger::Touch => presses.extend(touches()
+          .iter()
+          .filter(|t| t.phase == TouchPhase::Started)
+          .map(|t| PointerPress { position: t.position, touch_id: Some(t.id) })),
+        _ => (),
+      }
+    }
+    presses
+  }
+
+  pub fn get_movement_direction(&self) -> Vec2 {
+    let axis = |negative: Action, positive: Action| self.is_down(positive) as i32 as f32 - self.is_down(negative) as i32 as f32;
+    let direction = Vec2::new(axis(Action::MoveLeft, Action::MoveRight), axis(Action::MoveUp, Action::MoveDown)).normalize_or_zero();
+    if direction == Vec2::ZERO {
+      return gamepad::get_stick_direction();
+    }
+    direction
+  }
+}
+
+// Floating stick: a touch starting in the left half of the screen grabs it, centered where the finger went down.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualJoystick {
+  touch_id: Option<u64>,
+  center: Vec2,
+  knob: Vec2,
+}
+
+impl VirtualJoystick {
+  pub fn update(&mut self, touches: &[Touch]) {
+    for touch in touches {
+      match touch.phase {
+        TouchPhase::Started if self.touch_id.is_none() && touch.position.x < screen_width() / 2. => {
+          self.touch_id = Some(touch.id);
+          self.center = touch.position;
+          self.knob = touch.position;
+        },
+        TouchPhase::Moved | TouchPhase::Stationary if self.touch_id == Some(touch.id) => {
+          self.knob = self.center + (touch.position - self.center).clamp_length_max(JOYSTICK_RADIUS);
+        },
+        TouchPhase::Ended | TouchPhase::Cancelled if self.touch_id == Some(touch.id) => {
+          self.touch_id = None;
+        },
+        _ => (),
+      }
+    }
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.touch_id.is_some()
+  }
+
+  pub fn is_holding(&self, touch_id: Option<u64>) -> bool {
+    touch_id.is_some() && self.touch_id == touch_id
+  }
+
+  pub fn get_direction(&self) -> Vec2 {
+    if !self.is_active() {
+      return Vec2::ZERO;
+    }
+    let direction = (self.knob - self.center) / JOYSTICK_RADIUS;
+    if direction.length() < JOYSTICK_DEAD_ZONE { Vec2::ZERO } else { direction }
+  }
+
+  pub fn draw(&self) {
+    if self.is_active() {
+      draw_circle_lines(self.center.x, self.center.y, JOYSTICK_RADIUS, 3., Color::from_rgba(255, 255, 255, 120));
+      draw_circle(self.knob.x, self.knob.y, JOYSTICK_RADIUS * 0.4, Color::from_rgba(255, 255, 255, 160));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serialize_parse() {
+    let mut bindings = Bindings::default();
+    bindings.set(Action::ToggleDebug, vec![Trigger::Key(KeyCode::F1), Trigger::Mouse(MouseButton::Right)]);
+    bindings.set(Action::Back, vec![]);
+
+    assert_eq!(Bindings::parse(&bindings.serialize()), Ok(bindings));
+    assert_eq!(Bindings::parse("[controls]\nmove_up = i").map(|b| b.get(Action::MoveUp).clone()), Ok(vec![Trigger::Key(KeyCode::I)]));
+    assert_eq!(Bindings::parse("[controls]\nmove_to = touch").map(|b| b.get(Action::MoveTo).clone()), Ok(vec![Trigger::Touch]));
+    assert_eq!(Bindings::parse("[controls]\npause = PadStart").map(|b| b.get(Action::Pause).clone()), Ok(vec![Trigger::Gamepad(GamepadButton::Start)]));
+    assert!(Bindings::parse("[controls]\nmove_up = Joystick").is_err());
+    assert!(Bindings::parse("[controls]\nmove_up = PadJoystick").is_err());
+  }
+
+  #[test]
+  fn stage_actions_have_distinct_default_triggers() {
+    use Action::*;
+    // actions checked by the same stage, a shared trigger would fire both
+    let stages: [(&str, &[Action]); 5] = [
+      ("playing", &[MoveUp, MoveDown, MoveLeft, MoveRight, MoveTo, Pause, Back, ToggleDebug]),
+      ("replay", &[ToggleDebug, Pause, Back, Step]),
+      ("pause", &[Pause, Back]),
+      ("confirm", &[Confirm, Back]),
+      ("level_up", &[Choice1, Choice2, Choice3]),
+    ];
+    let bindings = Bindings::default();
+
+    for (stage, actions) in stages {
+      for (i, a) in actions.iter().enumerate() {
+        for b in &actions[i + 1..] {
+          let shared = bindings.get(*a).iter().find(|t| bindings.get(*b).contains(t));
+          assert!(shared.is_none(), "{} and {} share {:?} in {}", a.get_name(), b.get_name(), shared, stage);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn add_appends_new_triggers() {
+    let mut bindings = Bindings::default();
+    bindings.add(Action::ToggleDebug, Trigger::Key(KeyCode::F1));
+    bindings.add(Action::ToggleDebug, Trigger::Key(KeyCode::F1));
+
+    assert_eq!(bindings.get(Action::ToggleDebug), &vec![Trigger::Key(KeyCode::F3), Trigger::Key(KeyCode::F1)]);
+  }
+}
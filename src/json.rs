@@ -0,0 +1,197 @@
+use std::{iter::Peekable, str::Chars};
+
+// Just enough JSON for the metadata files exported by tools like Aseprite.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<JsonValue>),
+  Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
+    match self {
+      JsonValue::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+      _ => None,
+    }
+  }
+
+  pub fn as_f32(&self) -> Option<f32> {
+    match self {
+      JsonValue::Number(value) => Some(*value as f32),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      JsonValue::String(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+    match self {
+      JsonValue::Array(values) => Some(values),
+      _ => None,
+    }
+  }
+}
+
+struct Parser<'a> {
+  chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+  fn skip_whitespace(&mut self) {
+    while self.chars.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+      self.chars.next();
+    }
+  }
+
+  fn expect(&mut self, expected: char) -> Result<(), String> {
+    self.skip_whitespace();
+    match self.chars.next() {
+      Some(c) if c == expected => Ok(()),
+      Some(c) => Err(format!("expected '{}', found '{}'", expected, c)),
+      None => Err(format!("expected '{}', found end of input", expected)),
+    }
+  }
+
+  fn parse_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, String> {
+    for expected in literal.chars() {
+      if self.chars.next() != Some(expected) {
+        return Err(format!("invalid literal, expected {}", literal));
+      }
+    }
+    Ok(value)
+  }
+
+  fn parse_number(&mut self) -> Result<JsonValue, String> {
+    let mut number = String::new();
+    while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+      number.push(*c);
+      self.chars.next();
+    }
+    number.parse::<f64>().map(JsonValue::Number).map_err(|_| format!("invalid number: {}", number))
+  }
+
+  fn parse_string(&mut self) -> Result<String, String> {
+    self.expect('"')?;
+    let mut value = String::new();
+    loop {
+      match self.chars.next() {
+        Some('"') => return Ok(value),
+        Some('\\') => match self.chars.next() {
+          Some('n') => value.push('\n'),
+          Some('t') => value.push('\t'),
+          Some('r') => value.push('\r'),
+          Some('b') => value.push('\u{8}'),
+          Some('f') => value.push('\u{c}'),
+          Some('u') => {
+            let code: String = (0..4).filter_map(|_| self.chars.next()).collect();
+            let code = u32::from_str_radix(&code, 16).map_err(|_| format!("invalid unicode escape: {}", code))?;
+            value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+          },
+          Some(c) => value.push(c),
+          None => return Err("unterminated string".to_owned()),
+        },
+        Some(c) => value.push(c),
+        None => return Err("unterminated string".to_owned()),
+      }
+    }
+  }
+
+  fn parse_array(&mut self) -> Result<JsonValue, String> {
+    self.expect('[')?;
+    let mut values = vec![];
+    self.skip_whitespace();
+    if self.chars.peek() == Some(&']') {
+      self.chars.next();
+      return Ok(JsonValue::Array(values));
+    }
+    loop {
+      values.push(self.parse_value()?);
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some(',') => continue,
+        Some(']') => return Ok(JsonValue::Array(values)),
+        _ => return Err("expected ',' or ']' in array".to_owned()),
+      }
+    }
+  }
+
+  fn parse_object(&mut self) -> Result<JsonValue, String> {
+    self.expect('{')?;
+    let mut entries = vec![];
+    self.skip_whitespace();
+    if self.chars.peek() == Some(&'}') {
+      self.chars.next();
+      return Ok(JsonValue::Object(entries));
+    }
+    loop {
+      self.skip_whitespace();
+      let key = self.parse_string()?;
+      self.expect(':')?;
+      entries.push((key, self.parse_value()?));
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some(',') => continue,
+        Some('}') => return Ok(JsonValue::Object(entries)),
+        _ => return Err("expected ',' or '}' in object".to_owned()),
+      }
+    }
+  }
+
+  fn parse_value(&mut self) -> Result<JsonValue, String> {
+    self.skip_whitespace();
+    match self.chars.peek() {
+      Some('{') => self.parse_object(),
+      Some('[') => self.parse_array(),
+      Some('"') => self.parse_string().map(JsonValue::String),
+      Some('t') => self.parse_literal("true", JsonValue::Bool(true)),
+      Some('f') => self.parse_literal("false", JsonValue::Bool(false)),
+      Some('n') => self.parse_literal("null", JsonValue::Null),
+      Some(c) if c.is_ascii_digit() || *c == '-' => self.parse_number(),
+      Some(c) => Err(format!("unexpected character '{}'", c)),
+      None => Err("unexpected end of input".to_owned()),
+    }
+  }
+}
+
+pub fn parse_json(data: &str) -> Result<JsonValue, String> {
+  let mut parser = Parser { chars: data.chars().peekable() };
+  let value = parser.parse_value()?;
+  parser.skip_whitespace();
+  match parser.chars.next() {
+    Some(c) => Err(format!("unexpected trailing character '{}'", c)),
+    None => Ok(value),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_values() {
+    let value = parse_json(r#" { "a": [1, -2.5e1, true, null], "b": { "c": "x\"yA" }, "d": [] } "#).expect("json should be parsed");
+
+    assert_eq!(value.get("a"), Some(&JsonValue::Array(vec![
+      JsonValue::Number(1.), JsonValue::Number(-25.), JsonValue::Bool(true), JsonValue::Null,
+    ])));
+    assert_eq!(value.get("b").and_then(|b| b.get("c")).and_then(|c| c.as_str()), Some("x\"yA"));
+    assert_eq!(value.get("d").and_then(|d| d.as_array()).map(|d| d.len()), Some(0));
+  }
+
+  #[test]
+  fn reject_invalid() {
+    assert!(parse_json("{\"a\": }").is_err());
+    assert!(parse_json("[1, 2").is_err());
+    assert!(parse_json("\"open").is_err());
+    assert!(parse_json("{} x").is_err());
+  }
+}
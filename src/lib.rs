@@ -0,0 +1,22 @@
+#![allow(clippy::new_without_default)]
+
+pub mod systems;
+pub mod world_module;
+pub mod display;
+pub mod utils;
+pub mod player;
+pub mod stage_module;
+pub mod simulation;
+pub mod replay;
+pub mod snapshot;
+pub mod config;
+pub mod director;
+pub mod upgrade;
+pub mod settings;
+pub mod storage;
+pub mod highscore;
+pub mod input;
+pub mod json;
+pub mod palette;
+pub mod lifecycle;
+pub mod gamepad;
@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use macroquad::prelude::*;
+
+// Set for the frame in which the app lost focus, went to the background or was asked to close.
+static FOCUS_LOST: AtomicBool = AtomicBool::new(false);
+
+// macroquad 0.3 handles miniquad's minimized/restored events itself without forwarding them, so
+// each platform is polled for its own signal. Linux has no reliable way to tell whether the active
+// X11 window is ours (miniquad does not set _NET_WM_PID), so there only close requests are reported.
+pub fn update() {
+  FOCUS_LOST.store(poll_focus_lost() || is_quit_requested(), Ordering::Relaxed);
+}
+
+pub fn is_focus_lost() -> bool {
+  FOCUS_LOST.load(Ordering::Relaxed)
+}
+
+// Implemented by the `kgg_lifecycle` plugin registered in index.html, set on `blur` and on
+// `visibilitychange` to hidden.
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+  fn kgg_take_focus_lost() -> i32;
+}
+
+#[cfg(target_arch = "wasm32")]
+fn poll_focus_lost() -> bool {
+  unsafe { kgg_take_focus_lost() != 0 }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static WAS_FOREGROUND: AtomicBool = AtomicBool::new(true);
+
+#[cfg(not(target_arch = "wasm32"))]
+fn poll_focus_lost() -> bool {
+  let foreground = is_foreground();
+  WAS_FOREGROUND.swap(foreground, Ordering::Relaxed) && !foreground
+}
+
+// `ActivityManager.getMyMemoryState` reports the importance of the app process, it drops below
+// foreground as soon as the activity is paused.
+#[cfg(target_os = "android")]
+fn is_foreground() -> bool {
+  use macroquad::miniquad::native::android::attach_jni_env;
+
+  const IMPORTANCE_FOREGROUND: i32 = 100;
+
+  unsafe {
+    let env = attach_jni_env();
+    let info_class = (**env).FindClass.unwrap()(env, b"android/app/ActivityManager$RunningAppProcessInfo\0".as_ptr() as _);
+    let constructor = (**env).GetMethodID.unwrap()(env, info_class, b"<init>\0".as_ptr() as _, b"()V\0".as_ptr() as _);
+    let info = (**env).NewObject.unwrap()(env, info_class, constructor);
+
+    let manager_class = (**env).FindClass.unwrap()(env, b"android/app/ActivityManager\0".as_ptr() as _);
+    let get_memory_state = (**env).GetStaticMethodID.unwrap()(
+      env,
+      manager_class,
+      b"getMyMemoryState\0".as_ptr() as _,
+      b"(Landroid/app/ActivityManager$RunningAppProcessInfo;)V\0".as_ptr() as _,
+    );
+    (**env).CallStaticVoidMethod.unwrap()(env, manager_class, get_memory_state, info);
+
+    let importance = (**env).GetFieldID.unwrap()(env, info_class, b"importance\0".as_ptr() as _, b"I\0".as_ptr() as _);
+    let importance = (**env).GetIntField.unwrap()(env, info, importance);
+
+    (**env).DeleteLocalRef.unwrap()(env, info);
+    (**env).DeleteLocalRef.unwrap()(env, manager_class);
+    (**env).DeleteLocalRef.unwrap()(env, info_class);
+
+    importance <= IMPORTANCE_FOREGROUND
+  }
+}
+
+// The foreground window belongs to another process once the game window loses focus.
+#[cfg(target_os = "windows")]
+fn is_foreground() -> bool {
+  use std::ffi::c_void;
+
+  #[link(name = "user32")]
+  extern "system" {
+    fn GetForegroundWindow() -> *mut c_void;
+    fn GetWindowThreadProcessId(window: *mut c_void, process_id: *mut u32) -> u32;
+  }
+
+  unsafe {
+    let mut process_id = 0;
+    GetWindowThreadProcessId(GetForegroundWindow(), &mut process_id);
+    process_id == std::process::id()
+  }
+}
+
+// `[NSApp isActive]` turns false as soon as another application is activated.
+#[cfg(target_os = "macos")]
+fn is_foreground() -> bool {
+  use std::ffi::{c_char, c_void};
+
+  #[link(name = "objc")]
+  extern "C" {
+    fn objc_getClass(name: *const c_char) -> *mut c_void;
+    fn sel_registerName(name: *const c_char) -> *mut c_void;
+    fn objc_msgSend();
+  }
+
+  unsafe {
+    let send_object: unsafe extern "C" fn(*mut c_void, *mut c_void) -> *mut c_void = std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    let send_bool: unsafe extern "C" fn(*mut c_void, *mut c_void) -> i8 = std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    let app = send_object(objc_getClass(b"NSApplication\0".as_ptr() as _), sel_registerName(b"sharedApplication\0".as_ptr() as _));
+    app.is_null() || send_bool(app, sel_registerName(b"isActive\0".as_ptr() as _)) != 0
+  }
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "android", target_os = "windows", target_os = "macos")))]
+fn is_foreground() -> bool {
+  true
+}
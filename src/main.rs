@@ -1,13 +1,16 @@
 use macroquad::prelude::*;
-use stage_module::{stage_stack::StageStack, resources::Resources, playing::PlayingStage, main_menu::MainMenu};
-
-
-mod systems;
-mod world_module;
-mod display;
-mod utils;
-mod player;
-mod stage_module;
+use kg_g::{
+  stage_module::{stage_stack::StageStack, resources::Resources, playing::PlayingStage, main_menu::MainMenu},
+  simulation::Simulation,
+  systems::{rng::generate_seed, atlas::{Atlas, set_atlas}},
+  snapshot::{self, SNAPSHOT_PATH},
+  world_module::{enemy::EnemyTable, pickup::DropTable},
+  director::WaveScript,
+  replay::hash_tables,
+  settings::Settings,
+  lifecycle,
+  gamepad,
+};
 
 
 fn window_conf() -> Conf {
@@ -16,28 +19,61 @@ fn window_conf() -> Conf {
       window_width: 640,
       window_height: 480,
       high_dpi: false,
+      fullscreen: Settings::load_or_default().fullscreen,
       ..Default::default()
   }
 }
 
+fn get_seed_arg() -> Option<u64> {
+  let args: Vec<String> = std::env::args().collect();
+  args.iter()
+    .position(|arg| arg == "--seed")
+    .and_then(|i| args.get(i + 1))
+    .and_then(|seed| seed.parse().ok())
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
   set_pc_assets_folder("assets");
   let image = load_texture("frames.png").await.expect("frames.png should be loaded").get_texture_data();
+  let atlas = load_string("frames.json").await.expect("frames.json should be loaded");
+  let atlas = Atlas::parse(&atlas).expect("frames.json should be valid");
+
+  let enemies_ini = load_string("enemies.ini").await.expect("enemies.ini should be loaded");
+  let enemies = EnemyTable::parse(&enemies_ini).expect("enemies.ini should be valid");
+  let waves_ini = load_string("waves.ini").await.expect("waves.ini should be loaded");
+  let waves = WaveScript::parse(&waves_ini).expect("waves.ini should be valid");
+  waves.validate(&enemies).expect("waves.ini should only use enemies from enemies.ini");
+  enemies.validate_sprites(&atlas).expect("enemies.ini should only use sprites from frames.json");
+  set_atlas(atlas);
 
-  let resources = Resources::new(image);
-  let playing_stage = PlayingStage::new(&resources);
-  let mainmenu_stage = MainMenu {};
+  let drops_ini = load_string("pickups.ini").await.expect("pickups.ini should be loaded");
+  let drops = DropTable::parse(&drops_ini).expect("pickups.ini should be valid");
+
+  let tables_hash = hash_tables(&[&enemies_ini, &waves_ini, &drops_ini]);
+  let resources = Resources::new(image, enemies, waves, drops, tables_hash);
+  let playing_stage = match snapshot::load_from_file::<Simulation>(SNAPSHOT_PATH) {
+    Ok(simulation) if get_seed_arg().is_none() => PlayingStage::from_simulation(&resources, simulation),
+    _ => PlayingStage::new(&resources, get_seed_arg().unwrap_or_else(generate_seed)),
+  };
+  let mainmenu_stage = MainMenu::new();
   let mut stage_stack = StageStack::new().with_stages(vec![Box::new(mainmenu_stage), Box::new(playing_stage)]);
+  prevent_quit();
 
   loop {
     if stage_stack.is_empty() {
       break;
     }
 
+    lifecycle::update();
+    gamepad::update();
     stage_stack.update(&resources);
     stage_stack.draw(&resources);
 
+    if is_quit_requested() {
+      break;
+    }
+
     #[cfg(debug_assertions)]
     macroquad_profiler::profiler(Default::default());
 
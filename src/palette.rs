@@ -0,0 +1,97 @@
+use macroquad::{prelude::*, miniquad::{BlendState, BlendFactor, BlendValue, Equation, PipelineParams, UniformType}};
+
+use crate::utils::ReplaceColors;
+
+const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying mediump vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+  gl_Position = Projection * Model * vec4(position, 1);
+  color = color0 / 255.0;
+  uv = texcoord;
+}"#;
+
+// the key colors have to match the ones replaced by utils::ReplaceColors
+const FRAGMENT: &str = r#"#version 100
+precision mediump float;
+
+varying mediump vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+uniform vec4 armor;
+uniform vec4 detail;
+uniform vec4 skin;
+uniform vec4 eyes;
+
+const vec3 ARMOR_KEY = vec3(245.0, 115.0, 147.0) / 255.0;
+const vec3 DETAIL_KEY = vec3(245.0, 135.0, 147.0) / 255.0;
+const vec3 SKIN_KEY = vec3(245.0, 145.0, 147.0) / 255.0;
+const vec3 EYES_KEY = vec3(245.0, 95.0, 147.0) / 255.0;
+
+bool is_key(vec3 texel, vec3 key) {
+  return all(lessThan(abs(texel - key), vec3(0.01)));
+}
+
+void main() {
+  vec4 texel = texture2D(Texture, uv);
+  if (is_key(texel.rgb, ARMOR_KEY)) {
+    texel = vec4(armor.rgb, texel.a * armor.a);
+  } else if (is_key(texel.rgb, DETAIL_KEY)) {
+    texel = vec4(detail.rgb, texel.a * detail.a);
+  } else if (is_key(texel.rgb, SKIN_KEY)) {
+    texel = vec4(skin.rgb, texel.a * skin.a);
+  } else if (is_key(texel.rgb, EYES_KEY)) {
+    texel = vec4(eyes.rgb, texel.a * eyes.a);
+  }
+  gl_FragColor = color * texel;
+}"#;
+
+const UNIFORMS: [&str; 4] = ["armor", "detail", "skin", "eyes"];
+
+// Replaces the magic colors of the sprite sheet at draw time, so one base texture
+// serves every palette. Platforms without shader support fall back to customize_image.
+pub struct PaletteShader {
+  material: Material,
+}
+
+impl PaletteShader {
+  pub fn load() -> Result<Self, String> {
+    let material = load_material(
+      VERTEX,
+      FRAGMENT,
+      MaterialParams {
+        uniforms: UNIFORMS.iter().map(|name| (name.to_string(), UniformType::Float4)).collect(),
+        pipeline_params: PipelineParams {
+          color_blend: Some(BlendState::new(
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+          )),
+          ..Default::default()
+        },
+        ..Default::default()
+      },
+    ).map_err(|e| format!("{:?}", e))?;
+    Ok(Self { material })
+  }
+
+  pub fn apply(&self, colors: &ReplaceColors) {
+    gl_use_material(self.material);
+    for (name, color) in UNIFORMS.iter().zip(colors.get_colors()) {
+      self.material.set_uniform(name, color.to_vec());
+    }
+  }
+
+  pub fn reset(&self) {
+    gl_use_default_material();
+  }
+}
@@ -1,37 +1,88 @@
-use std::cmp::Ordering;
+use crate::{world_module::{actor::Actor, projectile::Projectile, weapon::Weapon}, systems::{timer::Timer, cd::{LAYER_PLAYER, MASK_PLAYER}}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}, upgrade::Upgrade};
 
-use macroquad::{prelude::*};
-
-use crate::{world_module::{actor::Actor, projectile::{Projectile, spawn_projectile_from_actor}}, systems::timer::Timer};
+const XP_BASE: usize = 5;
+const XP_PER_LEVEL: usize = 5;
+const MOVE_SPEED_UPGRADE: f32 = 1.1;
+const FIRE_RATE_UPGRADE: f32 = 1.15;
 
 pub struct Player {
   pub actor: Actor,
-  pub projectile_timer: Timer,
+  pub weapons: Vec<Weapon>,
+  pub fire_rate: f32,
   pub invulnerability_timer: Timer,
   pub invlunerable: bool,
+  pub xp: usize,
+  pub level: usize,
+  pub pending_level_ups: usize,
 }
 
 
 impl Player {
-  pub fn new(actor: Actor, projectile_timeout: f32) -> Self {
+  pub fn new(mut actor: Actor) -> Self {
+    actor.cd_bounds.set_layer(LAYER_PLAYER, MASK_PLAYER);
     Self {
       actor,
-      projectile_timer: Timer::new_timeout(projectile_timeout),
+      weapons: vec![],
+      fire_rate: 1.,
       invulnerability_timer: Timer::new_timeout(0.5),
       invlunerable: false,
+      xp: 0,
+      level: 1,
+      pending_level_ups: 0,
+    }
+  }
+
+  pub fn with_weapon(mut self, weapon: Weapon) -> Self {
+    self.weapons.push(weapon);
+    self
+  }
+
+  pub fn get_xp_to_next_level(&self) -> usize {
+    XP_BASE + (self.level - 1) * XP_PER_LEVEL
+  }
+
+  pub fn add_xp(&mut self, amount: usize) {
+    self.xp += amount;
+    while self.xp >= self.get_xp_to_next_level() {
+      self.xp -= self.get_xp_to_next_level();
+      self.level += 1;
+      self.pending_level_ups += 1;
     }
   }
 
-  pub fn modify_hp(&mut self, source: usize, amount: isize) {
+  pub fn apply_upgrade(&mut self, upgrade: &Upgrade) {
+    match upgrade {
+      Upgrade::NewWeapon(kind) => self.weapons.push(Weapon::new(*kind)),
+      Upgrade::WeaponLevel(kind) => {
+        if let Some(weapon) = self.weapons.iter_mut().find(|w| w.kind == *kind) {
+          weapon.level_up();
+        }
+      },
+      Upgrade::MoveSpeed => {
+        let speed = self.actor.movable.get_speed();
+        self.actor.movable.set_speed(speed * MOVE_SPEED_UPGRADE);
+      },
+      Upgrade::MaxHp => self.actor.hp.increase_max(1),
+      Upgrade::FireRate => self.fire_rate *= FIRE_RATE_UPGRADE,
+    }
+  }
+
+  pub fn heal(&mut self, source: usize, amount: isize) {
+    let amount = amount.min(self.actor.hp.max_hp - self.actor.hp.act_hp);
+    if amount > 0 {
+      self.actor.hp.modify(source, source, amount);
+    }
+  }
+
+  pub fn modify_hp(&mut self, source: usize, source_origin: usize, amount: isize) {
     if !self.invlunerable {
-      self.actor.hp.modify(source, 0, amount);
+      self.actor.hp.modify(source, source_origin, amount);
       self.invlunerable = true;
       self.invulnerability_timer.reset();
     }
   }
 
   pub fn update(&mut self, delta_t: f32, projectiles: &mut Vec<Projectile>, enemies: &[Actor]) {
-    self.projectile_timer.update(delta_t);
     if self.invulnerability_timer.is_over() {
       self.invlunerable = false;
     }
@@ -39,18 +90,34 @@ impl Player {
       self.invulnerability_timer.update(delta_t);
     }
 
-    if self.projectile_timer.is_over() {
-      let player_position = &self.actor.movable.position;
+    for weapon in self.weapons.iter_mut() {
+      weapon.update(delta_t, self.fire_rate, &self.actor, enemies, projectiles);
+    }
+  }
+}
 
-      if let Some(closest) = enemies.iter().min_by(|e_a, e_b| {
-        let d_a = player_position.distance_squared(e_a.movable.position);
-        let d_b = player_position.distance_squared(e_b.movable.position);
+impl Snapshot for Player {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_snapshot(&self.actor);
+    writer.write_list(&self.weapons);
+    writer.write(self.fire_rate);
+    writer.write_snapshot(&self.invulnerability_timer);
+    writer.write_bool(self.invlunerable);
+    writer.write(self.xp);
+    writer.write(self.level);
+    writer.write(self.pending_level_ups);
+  }
 
-        d_a.partial_cmp(&d_b).unwrap_or_else(|| Ordering::Equal)
-      }) {
-        projectiles.push(spawn_projectile_from_actor(self.actor.get_id(), player_position, &closest.movable.position));
-        self.projectile_timer.reset();
-      }
-    }
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      actor: reader.read_snapshot()?,
+      weapons: reader.read_list()?,
+      fire_rate: reader.read()?,
+      invulnerability_timer: reader.read_snapshot()?,
+      invlunerable: reader.read_bool()?,
+      xp: reader.read()?,
+      level: reader.read()?,
+      pending_level_ups: reader.read()?,
+    })
   }
 }
@@ -0,0 +1,222 @@
+use macroquad::prelude::*;
+
+use crate::{simulation::Simulation, input::InputEvent, storage, world_module::{enemy::DEFAULT_ENEMIES, pickup::DEFAULT_PICKUPS}, director::DEFAULT_WAVES};
+
+const REPLAY_HEADER: &str = "kg-g-replay";
+const REPLAY_VERSION: u32 = 2;
+
+pub const REPLAY_PATH: &str = "last_replay.txt";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameRecord {
+  pub frame: usize,
+  pub delta_t: f32,
+  pub inputs: Vec<InputEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+  pub seed: u64,
+  pub viewport: (f32, f32),
+  // hash of the enemies, waves and pickups tables the run was recorded with
+  pub tables: u64,
+  pub frames: Vec<FrameRecord>,
+}
+
+// FNV-1a over the ini sources, stable across builds unlike std's DefaultHasher
+pub fn hash_tables(sources: &[&str]) -> u64 {
+  sources.iter().fold(0xcbf29ce484222325, |hash, source| {
+    source.bytes().chain(std::iter::once(0)).fold(hash, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+  })
+}
+
+pub fn get_default_tables_hash() -> u64 {
+  hash_tables(&[DEFAULT_ENEMIES, DEFAULT_WAVES, DEFAULT_PICKUPS])
+}
+
+impl Replay {
+  pub fn new(seed: u64, viewport: (f32, f32), tables: u64) -> Self {
+    Self { seed, viewport, tables, frames: vec![] }
+  }
+
+  pub fn record(&mut self, delta_t: f32, inputs: &[InputEvent]) {
+    self.frames.push(FrameRecord { frame: self.frames.len(), delta_t, inputs: inputs.to_vec() });
+  }
+
+  pub fn create_simulation(&self) -> Simulation {
+    Simulation::new(self.viewport, self.seed)
+  }
+
+  // the simulation should use the tables the replay was recorded with, see check_tables
+  pub fn play_headless(&self, mut simulation: Simulation) -> Simulation {
+    for record in &self.frames {
+      simulation.update_with_inputs(record.delta_t, &record.inputs);
+    }
+    simulation
+  }
+
+  pub fn check_tables(&self, tables: u64) -> Result<(), String> {
+    if self.tables != tables {
+      return Err("replay was recorded with different enemies, waves or pickups tables".to_owned());
+    }
+    Ok(())
+  }
+
+  pub fn serialize(&self) -> String {
+    let mut lines = vec![
+      format!("{} {}", REPLAY_HEADER, REPLAY_VERSION),
+      format!("seed {}", self.seed),
+      format!("viewport {} {}", self.viewport.0, self.viewport.1),
+      format!("tables {:016x}", self.tables),
+    ];
+    for record in &self.frames {
+      let mut line = format!("f {} {}", record.frame, record.delta_t);
+      for input in &record.inputs {
+        match input {
+          InputEvent::MoveTo(position) => line.push_str(&format!(" move {} {}", position.x, position.y)),
+          InputEvent::Steer(direction) => line.push_str(&format!(" steer {} {}", direction.x, direction.y)),
+          InputEvent::ChooseUpgrade(index) => line.push_str(&format!(" upgrade {}", index)),
+        }
+      }
+      lines.push(line);
+    }
+    lines.join("\n")
+  }
+
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let mut lines = data.lines().filter(|l| !l.trim().is_empty());
+
+    match lines.next().map(|l| l.split_whitespace().collect::<Vec<&str>>()).as_deref() {
+      Some([REPLAY_HEADER, version]) if version.parse::<u32>() == Ok(REPLAY_VERSION) => (),
+      _ => return Err("unsupported replay header".to_owned()),
+    }
+
+    let mut replay = Replay::new(0, (0., 0.), 0);
+    for line in lines {
+      let tokens: Vec<&str> = line.split_whitespace().collect();
+      match tokens.as_slice() {
+        ["seed", seed] => replay.seed = parse_token(seed)?,
+        ["viewport", w, h] => replay.viewport = (parse_token(w)?, parse_token(h)?),
+        ["tables", hash] => replay.tables = u64::from_str_radix(hash, 16).map_err(|_| format!("invalid value: {}", hash))?,
+        ["f", frame, delta_t, inputs @ ..] => {
+          let frame = parse_token(frame)?;
+          if frame != replay.frames.len() {
+            return Err(format!("expected frame {}, found {}", replay.frames.len(), frame));
+          }
+          replay.frames.push(FrameRecord { frame, delta_t: parse_token(delta_t)?, inputs: parse_inputs(inputs)? });
+        },
+        _ => return Err(format!("invalid replay line: {}", line)),
+      }
+    }
+
+    Ok(replay)
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), String> {
+    storage::write_string(path, &self.serialize())
+  }
+
+  pub fn load(path: &str) -> Result<Self, String> {
+    storage::read_string(path).and_then(|data| Self::parse(&data))
+  }
+}
+
+fn parse_token<T: std::str::FromStr>(token: &str) -> Result<T, String> {
+  token.parse::<T>().map_err(|_| format!("invalid value: {}", token))
+}
+
+fn parse_inputs(tokens: &[&str]) -> Result<Vec<InputEvent>, String> {
+  let mut inputs = vec![];
+  let mut rest = tokens;
+  while !rest.is_empty() {
+    match rest {
+      ["move", x, y, tail @ ..] => {
+        inputs.push(InputEvent::MoveTo(Vec2::new(parse_token(x)?, parse_token(y)?)));
+        rest = tail;
+      },
+      ["steer", x, y, tail @ ..] => {
+        inputs.push(InputEvent::Steer(Vec2::new(parse_token(x)?, parse_token(y)?)));
+        rest = tail;
+      },
+      ["upgrade", index, tail @ ..] => {
+        inputs.push(InputEvent::ChooseUpgrade(parse_token(index)?));
+        rest = tail;
+      },
+      _ => return Err(format!("invalid input: {}", rest.join(" "))),
+    }
+  }
+  Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{simulation::{FIXED_DELTA, HEADLESS_VIEWPORT}, upgrade::UPGRADE_CHOICES};
+
+  fn record_run(seed: u64) -> (Replay, Simulation) {
+    let mut simulation = Simulation::new(HEADLESS_VIEWPORT, seed);
+    let mut replay = Replay::new(seed, HEADLESS_VIEWPORT, get_default_tables_hash());
+    let targets = [Vec2::new(200., 0.), Vec2::new(-150., 120.), Vec2::new(0., -300.)];
+
+    for frame in 0..60 * 30 {
+      let mut inputs = match frame % 90 {
+        0 => vec![InputEvent::MoveTo(targets[(frame / 90) % targets.len()])],
+        45 => vec![InputEvent::Steer(Vec2::new(-1., 1.).normalize())],
+        60 => vec![InputEvent::Steer(Vec2::ZERO)],
+        _ => vec![],
+      };
+      if simulation.is_waiting_for_upgrade() {
+        inputs.push(InputEvent::ChooseUpgrade(frame % UPGRADE_CHOICES));
+      }
+      let delta_t = FIXED_DELTA + (frame % 3) as f32 * 0.001;
+      replay.record(delta_t, &inputs);
+      simulation.update_with_inputs(delta_t, &inputs);
+      if simulation.is_over() {
+        break;
+      }
+    }
+
+    (replay, simulation)
+  }
+
+  #[test]
+  fn serialize_parse() {
+    let (replay, _) = record_run(3);
+    let parsed = Replay::parse(&replay.serialize()).expect("replay should be parsed");
+
+    assert_eq!(parsed, replay);
+  }
+
+  #[test]
+  fn replay_reproduces_run() {
+    let (replay, simulation) = record_run(3);
+    let parsed = Replay::parse(&replay.serialize()).expect("replay should be parsed");
+    assert!(parsed.check_tables(get_default_tables_hash()).is_ok());
+    let replayed = parsed.play_headless(parsed.create_simulation());
+
+    assert_eq!(replayed.world.score, simulation.world.score);
+    assert_eq!(replayed.difficulty, simulation.difficulty);
+    assert_eq!(replayed.world.player.actor.hp.act_hp, simulation.world.player.actor.hp.act_hp);
+    assert_eq!(replayed.world.player.actor.movable.position, simulation.world.player.actor.movable.position);
+  }
+
+  #[test]
+  fn edited_tables_are_rejected() {
+    let replay = Replay::new(3, HEADLESS_VIEWPORT, get_default_tables_hash());
+    let edited = DEFAULT_ENEMIES.replace("hp = 2", "hp = 3");
+
+    assert_ne!(hash_tables(&[&edited, DEFAULT_WAVES, DEFAULT_PICKUPS]), get_default_tables_hash());
+    assert!(replay.check_tables(hash_tables(&[&edited, DEFAULT_WAVES, DEFAULT_PICKUPS])).is_err());
+    assert!(Replay::parse("kg-g-replay 2\nseed 3").map(|r| r.check_tables(get_default_tables_hash()).is_err()).unwrap_or(false));
+  }
+
+  #[test]
+  fn parse_invalid() {
+    assert!(Replay::parse("something else").is_err());
+    assert!(Replay::parse("kg-g-replay 1\nseed 3").is_err());
+    assert!(Replay::parse("kg-g-replay 2\ntables xyz").is_err());
+    assert!(Replay::parse("kg-g-replay 2\nf 0 0.1 jump").is_err());
+    assert!(Replay::parse("kg-g-replay 2\nf 0 0.1\nf 2 0.1").is_err());
+    assert!(Replay::parse("kg-g-replay 2\nf 1 0.1").is_err());
+  }
+}
@@ -0,0 +1,73 @@
+use crate::{config::parse_config, storage};
+
+pub const SETTINGS_PATH: &str = "settings.ini";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+  pub auto_pause: bool,
+  pub fullscreen: bool,
+  pub show_debug: bool,
+  pub virtual_joystick: bool,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      auto_pause: true,
+      fullscreen: false,
+      show_debug: false,
+      virtual_joystick: cfg!(target_os = "android"),
+    }
+  }
+}
+
+impl Settings {
+  pub fn serialize(&self) -> String {
+    [
+      "[settings]".to_owned(),
+      format!("auto_pause = {}", self.auto_pause),
+      format!("fullscreen = {}", self.fullscreen),
+      format!("show_debug = {}", self.show_debug),
+      format!("virtual_joystick = {}", self.virtual_joystick),
+    ].join("\n")
+  }
+
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let default = Self::default();
+    match parse_config(data)?.iter().find(|s| s.name == "settings") {
+      Some(section) => Ok(Self {
+        auto_pause: section.get_or("auto_pause", default.auto_pause)?,
+        fullscreen: section.get_or("fullscreen", default.fullscreen)?,
+        show_debug: section.get_or("show_debug", default.show_debug)?,
+        virtual_joystick: section.get_or("virtual_joystick", default.virtual_joystick)?,
+      }),
+      None => Ok(default),
+    }
+  }
+
+  pub fn load_or_default() -> Self {
+    Self::load(SETTINGS_PATH).unwrap_or_default()
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), String> {
+    storage::write_string(path, &self.serialize())
+  }
+
+  pub fn load(path: &str) -> Result<Self, String> {
+    storage::read_string(path).and_then(|data| Self::parse(&data))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serialize_parse() {
+    let settings = Settings { auto_pause: false, fullscreen: true, show_debug: true, virtual_joystick: true };
+
+    assert_eq!(Settings::parse(&settings.serialize()), Ok(settings));
+    assert_eq!(Settings::parse("[settings]\nfullscreen = true"), Ok(Settings { fullscreen: true, ..Default::default() }));
+    assert!(Settings::parse("[settings]\nfullscreen = maybe").is_err());
+  }
+}
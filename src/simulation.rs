@@ -0,0 +1,278 @@
+use macroquad::prelude::*;
+
+use crate::{world_module::{world::World, actor::Actor, enemy::EnemyTable, pickup::DropTable, weapon::{Weapon, WeaponKind}}, player::Player, systems::{ai::{Ai, WeightedStates}, rng::Rng}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}, input::InputEvent, director::{SpawnDirector, WaveScript}, upgrade::{Upgrade, UPGRADE_CHOICES, roll_upgrades}};
+
+pub const FIXED_DELTA: f32 = 1. / 60.;
+pub const HEADLESS_VIEWPORT: (f32, f32) = (640., 480.);
+
+const BENCHMARK_SPACING: f32 = 32.;
+
+pub struct Simulation {
+  pub world: World,
+  pub difficulty: usize,
+  pub time: f32,
+  seed: u64,
+  viewport: (f32, f32),
+  enemies: EnemyTable,
+  waves: WaveScript,
+  director: SpawnDirector,
+  upgrade_choices: Vec<Upgrade>,
+}
+
+impl Simulation {
+  pub fn new(viewport: (f32, f32), seed: u64) -> Self {
+    let player_position = Vec2::new(0., 0.);
+    let player_actor = Actor::new(player_position, 100., 5);
+    let player = Player::new(player_actor).with_weapon(Weapon::new(WeaponKind::Bolt));
+
+    Self {
+      difficulty: 0,
+      time: 0.,
+      seed,
+      world: World::new(player, Rng::new(seed)),
+      viewport,
+      enemies: EnemyTable::default(),
+      waves: WaveScript::default(),
+      director: SpawnDirector::new(),
+      upgrade_choices: vec![],
+    }
+  }
+
+  pub fn with_enemy_table(mut self, enemies: EnemyTable) -> Self {
+    self.enemies = enemies;
+    self
+  }
+
+  pub fn with_wave_script(mut self, waves: WaveScript) -> Self {
+    self.waves = waves;
+    self
+  }
+
+  pub fn with_drop_table(mut self, drops: DropTable) -> Self {
+    self.world.drops = drops;
+    self
+  }
+
+  pub fn with_enemies(mut self, count: usize) -> Self {
+    let bounds = self.world.bounds;
+    let columns = ((bounds.w - BENCHMARK_SPACING * 2.) / BENCHMARK_SPACING) as usize;
+
+    let mut ai_actors: Vec<(Actor, Ai)> = vec![];
+    for c in 0..count {
+      let x_mod = (c % columns) as f32;
+      let y_mod = (c / columns) as f32;
+      let position = Vec2::new(bounds.left() + BENCHMARK_SPACING * (1. + x_mod), bounds.top() + BENCHMARK_SPACING * (1. + y_mod));
+      let actor = Actor::new(position, 80., 2);
+      let ai = Ai::new(WeightedStates::new_idle_wandering(&[1, 5, 30]), &mut self.world.rng);
+      ai_actors.push((actor, ai));
+    }
+
+    self.world = self.world.with_ai_actors(ai_actors);
+    self
+  }
+
+  pub fn get_seed(&self) -> u64 {
+    self.seed
+  }
+
+  pub fn is_over(&self) -> bool {
+    !self.world.player.actor.is_alive()
+  }
+
+  pub fn get_upgrade_choices(&self) -> &Vec<Upgrade> {
+    &self.upgrade_choices
+  }
+
+  pub fn is_waiting_for_upgrade(&self) -> bool {
+    !self.upgrade_choices.is_empty()
+  }
+
+  pub fn apply_input(&mut self, input: &InputEvent) {
+    match input {
+      InputEvent::MoveTo(position) => {
+        if self.world.bounds.contains(*position) {
+          self.world.on_mouse_button_down(*position);
+        }
+      },
+      InputEvent::Steer(direction) => self.world.steer_player(*direction),
+      InputEvent::ChooseUpgrade(index) => {
+        if let Some(upgrade) = self.upgrade_choices.get(*index).copied() {
+          self.world.player.apply_upgrade(&upgrade);
+          self.upgrade_choices.clear();
+        }
+      },
+    }
+  }
+
+  pub fn update_with_inputs(&mut self, delta_t: f32, inputs: &[InputEvent]) {
+    for input in inputs {
+      self.apply_input(input);
+    }
+    self.update(delta_t);
+  }
+
+  pub fn get_wave_name(&self) -> &str {
+    &self.director.get_wave(&self.waves).name
+  }
+
+  pub fn update(&mut self, delta_t: f32) {
+    if self.is_waiting_for_upgrade() {
+      return;
+    }
+
+    self.time += delta_t;
+    self.world.update(delta_t);
+
+    let player = &mut self.world.player;
+    if player.pending_level_ups > 0 {
+      player.pending_level_ups -= 1;
+      self.upgrade_choices = roll_upgrades(player, &mut self.world.rng, UPGRADE_CHOICES);
+    }
+
+    self.director.update(delta_t, &self.waves, &self.enemies, &mut self.world, self.viewport);
+    self.difficulty = self.director.get_difficulty(&self.waves);
+    self.world.stats.max_difficulty = self.world.stats.max_difficulty.max(self.difficulty);
+  }
+
+  pub fn run_headless(&mut self, delta_t: f32, max_frames: usize) -> usize {
+    let mut frame = 0;
+    while frame < max_frames && !self.is_over() {
+      if self.is_waiting_for_upgrade() {
+        self.apply_input(&InputEvent::ChooseUpgrade(0));
+      }
+      self.update(delta_t);
+      frame += 1;
+    }
+    frame
+  }
+}
+
+impl Snapshot for Simulation {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_snapshot(&self.world);
+    writer.write(self.difficulty);
+    writer.write(self.time);
+    writer.write(self.seed);
+    writer.write(self.viewport.0);
+    writer.write(self.viewport.1);
+    writer.write_snapshot(&self.director);
+    writer.write_list(&self.upgrade_choices);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      world: reader.read_snapshot()?,
+      difficulty: reader.read()?,
+      time: reader.read()?,
+      seed: reader.read()?,
+      viewport: (reader.read()?, reader.read()?),
+      enemies: EnemyTable::default(),
+      waves: WaveScript::default(),
+      director: reader.read_snapshot()?,
+      upgrade_choices: reader.read_list()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::snapshot;
+
+  const MAX_FRAMES: usize = 60 * 60 * 10;
+  const SEED: u64 = 1234;
+
+  #[test]
+  fn run_headless_until_game_over() {
+    let mut simulation = Simulation::new(HEADLESS_VIEWPORT, SEED);
+    let frames = simulation.run_headless(FIXED_DELTA, MAX_FRAMES);
+
+    assert!(frames < MAX_FRAMES);
+    assert!(simulation.is_over());
+    assert!(simulation.difficulty > 0);
+
+    let stats = simulation.world.get_run_stats();
+    assert_eq!(stats.max_difficulty, simulation.difficulty);
+    assert!(stats.projectiles_fired >= stats.get_total_kills());
+    assert!(stats.damage_taken.iter().map(|(_, amount)| amount).sum::<isize>() >= 5);
+  }
+
+  #[test]
+  fn same_seed_same_run() {
+    let mut simulation_a = Simulation::new(HEADLESS_VIEWPORT, SEED);
+    let mut simulation_b = Simulation::new(HEADLESS_VIEWPORT, SEED);
+
+    let frames_a = simulation_a.run_headless(FIXED_DELTA, MAX_FRAMES);
+    let frames_b = simulation_b.run_headless(FIXED_DELTA, MAX_FRAMES);
+
+    assert_eq!(frames_a, frames_b);
+    assert_eq!(simulation_a.world.score, simulation_b.world.score);
+    assert_eq!(simulation_a.difficulty, simulation_b.difficulty);
+  }
+
+  #[test]
+  fn snapshot_restores_run() {
+    let mut simulation = Simulation::new(HEADLESS_VIEWPORT, SEED);
+    simulation.apply_input(&InputEvent::MoveTo(Vec2::new(150., -80.)));
+    simulation.run_headless(FIXED_DELTA, 60 * 15);
+
+    let mut restored: Simulation = snapshot::deserialize(&snapshot::serialize(&simulation)).expect("snapshot should be loaded");
+    assert_eq!(snapshot::serialize(&restored), snapshot::serialize(&simulation));
+
+    let frames = simulation.run_headless(FIXED_DELTA, MAX_FRAMES);
+    let restored_frames = restored.run_headless(FIXED_DELTA, MAX_FRAMES);
+
+    assert_eq!(restored_frames, frames);
+    assert_eq!(restored.world.score, simulation.world.score);
+    assert_eq!(restored.difficulty, simulation.difficulty);
+  }
+
+  #[test]
+  fn snapshot_rejects_other_versions() {
+    let simulation = Simulation::new(HEADLESS_VIEWPORT, SEED);
+    let data = snapshot::serialize(&simulation).replacen(&snapshot::SNAPSHOT_VERSION.to_string(), "0", 1);
+
+    assert!(snapshot::deserialize::<Simulation>(&data).is_err());
+  }
+
+  #[test]
+  fn level_up_waits_for_upgrade_choice() {
+    let mut simulation = Simulation::new(HEADLESS_VIEWPORT, SEED);
+    simulation.world.player.add_xp(simulation.world.player.get_xp_to_next_level());
+    simulation.update(FIXED_DELTA);
+
+    assert_eq!(simulation.world.player.level, 2);
+    assert_eq!(simulation.get_upgrade_choices().len(), UPGRADE_CHOICES);
+
+    let time = simulation.time;
+    simulation.update(FIXED_DELTA);
+    assert_eq!(simulation.time, time);
+
+    simulation.update_with_inputs(FIXED_DELTA, &[InputEvent::ChooseUpgrade(0)]);
+    assert!(!simulation.is_waiting_for_upgrade());
+    assert!(simulation.time > time);
+  }
+
+  #[test]
+  fn player_input_is_clamped_to_bounds() {
+    let mut simulation = Simulation::new(HEADLESS_VIEWPORT, SEED);
+    simulation.apply_input(&InputEvent::MoveTo(Vec2::new(10000., 0.)));
+    assert!(!simulation.world.player.actor.movable.is_moving());
+
+    simulation.apply_input(&InputEvent::MoveTo(Vec2::new(100., 0.)));
+    assert!(simulation.world.player.actor.movable.is_moving());
+  }
+
+  #[test]
+  fn steering_stays_inside_bounds() {
+    for direction in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::new(-1., -1.)] {
+      let mut simulation = Simulation::new(HEADLESS_VIEWPORT, SEED);
+      for _ in 0..60 * 30 {
+        simulation.apply_input(&InputEvent::Steer(direction));
+        simulation.world.update(FIXED_DELTA);
+        assert!(simulation.world.bounds.contains(simulation.world.player.actor.movable.position));
+      }
+      assert!(simulation.world.player.actor.movable.is_moving());
+    }
+  }
+}
@@ -0,0 +1,123 @@
+use std::{fmt::Display, str::{FromStr, SplitWhitespace}};
+
+use macroquad::prelude::*;
+
+use crate::storage;
+
+const SNAPSHOT_HEADER: &str = "kg-g-snapshot";
+pub const SNAPSHOT_VERSION: u32 = 16;
+
+pub const SNAPSHOT_PATH: &str = "savegame.txt";
+
+pub trait Snapshot: Sized {
+  fn save(&self, writer: &mut SnapshotWriter);
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String>;
+}
+
+pub struct SnapshotWriter {
+  tokens: Vec<String>,
+}
+
+impl SnapshotWriter {
+  pub fn new() -> Self {
+    Self { tokens: vec![SNAPSHOT_HEADER.to_owned(), SNAPSHOT_VERSION.to_string()] }
+  }
+
+  pub fn write<T: Display>(&mut self, value: T) {
+    self.tokens.push(value.to_string());
+  }
+
+  pub fn write_bool(&mut self, value: bool) {
+    self.write(value as u8);
+  }
+
+  pub fn write_vec2(&mut self, value: &Vec2) {
+    self.write(value.x);
+    self.write(value.y);
+  }
+
+  pub fn write_rect(&mut self, value: &Rect) {
+    self.write(value.x);
+    self.write(value.y);
+    self.write(value.w);
+    self.write(value.h);
+  }
+
+  pub fn write_snapshot<T: Snapshot>(&mut self, value: &T) {
+    value.save(self);
+  }
+
+  pub fn write_list<T: Snapshot>(&mut self, values: &[T]) {
+    self.write(values.len());
+    for value in values {
+      value.save(self);
+    }
+  }
+
+  pub fn finish(self) -> String {
+    self.tokens.join(" ")
+  }
+}
+
+pub struct SnapshotReader<'a> {
+  tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> SnapshotReader<'a> {
+  pub fn new(data: &'a str) -> Result<Self, String> {
+    let mut reader = Self { tokens: data.split_whitespace() };
+    if reader.tokens.next() != Some(SNAPSHOT_HEADER) {
+      return Err("invalid snapshot header".to_owned());
+    }
+    let version = reader.read::<u32>()?;
+    if version != SNAPSHOT_VERSION {
+      return Err(format!("unsupported snapshot version {}, expected {}", version, SNAPSHOT_VERSION));
+    }
+    Ok(reader)
+  }
+
+  pub fn read<T: FromStr>(&mut self) -> Result<T, String> {
+    let token = self.tokens.next().ok_or_else(|| "unexpected end of snapshot".to_owned())?;
+    token.parse::<T>().map_err(|_| format!("invalid snapshot value: {}", token))
+  }
+
+  pub fn read_bool(&mut self) -> Result<bool, String> {
+    Ok(self.read::<u8>()? != 0)
+  }
+
+  pub fn read_vec2(&mut self) -> Result<Vec2, String> {
+    Ok(Vec2::new(self.read()?, self.read()?))
+  }
+
+  pub fn read_rect(&mut self) -> Result<Rect, String> {
+    Ok(Rect::new(self.read()?, self.read()?, self.read()?, self.read()?))
+  }
+
+  pub fn read_snapshot<T: Snapshot>(&mut self) -> Result<T, String> {
+    T::load(self)
+  }
+
+  pub fn read_list<T: Snapshot>(&mut self) -> Result<Vec<T>, String> {
+    let len = self.read::<usize>()?;
+    (0..len).map(|_| T::load(self)).collect()
+  }
+}
+
+pub fn serialize<T: Snapshot>(value: &T) -> String {
+  let mut writer = SnapshotWriter::new();
+  value.save(&mut writer);
+  writer.finish()
+}
+
+pub fn deserialize<T: Snapshot>(data: &str) -> Result<T, String> {
+  let mut reader = SnapshotReader::new(data)?;
+  T::load(&mut reader)
+}
+
+pub fn save_to_file<T: Snapshot>(path: &str, value: &T) -> Result<(), String> {
+  storage::write_string(path, &serialize(value))
+}
+
+pub fn load_from_file<T: Snapshot>(path: &str) -> Result<T, String> {
+  storage::read_string(path).and_then(|data| deserialize(&data))
+}
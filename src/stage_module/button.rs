@@ -0,0 +1,92 @@
+use macroquad::prelude::*;
+
+const SPACING: f32 = 8.;
+
+pub fn get_button_size() -> Vec2 {
+  Vec2::new((screen_width() * 0.3).clamp(160., 320.), (screen_height() * 0.09).max(40.))
+}
+
+fn get_font_size(height: f32) -> f32 {
+  (height * 0.5).round()
+}
+
+// Big immediate mode buttons that work with a finger, laid out again every frame from the screen size.
+// Each button carries the action the stage runs when it is clicked.
+pub struct Button<A: Copy> {
+  pub rect: Rect,
+  pub label: String,
+  pub action: A,
+}
+
+impl<A: Copy> Button<A> {
+  pub fn new(rect: Rect, label: &str, action: A) -> Self {
+    Self { rect, label: label.to_owned(), action }
+  }
+
+  // Buttons are widened to fit the longest label, up to the screen width.
+  pub fn column<L: ToString>(items: &[(L, A)], center_x: f32, top: f32) -> Vec<Self> {
+    let size = get_button_size();
+    let items: Vec<(String, A)> = items.iter().map(|(label, action)| (label.to_string(), *action)).collect();
+    let width = items
+      .iter()
+      .map(|(label, _)| measure_text(label, None, get_font_size(size.y) as u16, 1.).width + SPACING * 4.)
+      .fold(size.x, f32::max)
+      .min(screen_width() - SPACING * 2.);
+    items
+      .into_iter()
+      .enumerate()
+      .map(|(i, (label, action))| Self {
+        rect: Rect::new(center_x - width / 2., top + i as f32 * (size.y + SPACING), width, size.y),
+        label,
+        action,
+      })
+      .collect()
+  }
+
+  pub fn row<L: ToString>(items: &[(L, A)], center_x: f32, top: f32) -> Vec<Self> {
+    let size = get_button_size();
+    let width = ((screen_width() - SPACING * (items.len() + 1) as f32) / items.len() as f32).min(size.x);
+    let left = center_x - (width * items.len() as f32 + SPACING * (items.len() - 1) as f32) / 2.;
+    items
+      .iter()
+      .enumerate()
+      .map(|(i, (label, action))| Self {
+        rect: Rect::new(left + i as f32 * (width + SPACING), top, width, size.y),
+        label: label.to_string(),
+        action: *action,
+      })
+      .collect()
+  }
+
+  // Height of a column of `count` buttons, used to center it.
+  pub fn get_column_height(count: usize) -> f32 {
+    count as f32 * (get_button_size().y + SPACING) - SPACING
+  }
+
+  pub fn is_clicked(&self) -> bool {
+    let released_mouse = is_mouse_button_released(MouseButton::Left) && self.rect.contains(Vec2::from(mouse_position()));
+    let released_touch = touches().iter().any(|t| t.phase == TouchPhase::Ended && self.rect.contains(t.position));
+    released_mouse || released_touch
+  }
+
+  pub fn draw(&self) {
+    let hovered = self.rect.contains(Vec2::from(mouse_position()));
+    let color = if hovered { Color::from_rgba(90, 90, 110, 255) } else { Color::from_rgba(60, 60, 75, 255) };
+    draw_rectangle(self.rect.x, self.rect.y, self.rect.w, self.rect.h, color);
+    draw_rectangle_lines(self.rect.x, self.rect.y, self.rect.w, self.rect.h, 2., WHITE);
+
+    let font_size = get_font_size(self.rect.h);
+    let dimensions = measure_text(&self.label, None, font_size as u16, 1.);
+    draw_text(
+      &self.label,
+      self.rect.x + (self.rect.w - dimensions.width) / 2.,
+      self.rect.y + (self.rect.h + dimensions.height) / 2.,
+      font_size,
+      WHITE
+    );
+  }
+}
+
+pub fn clicked_button<A: Copy>(buttons: &[Button<A>]) -> Option<A> {
+  buttons.iter().find(|b| b.is_clicked()).map(|b| b.action)
+}
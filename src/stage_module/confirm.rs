@@ -0,0 +1,63 @@
+use macroquad::prelude::*;
+
+use crate::input::{Action, Bindings};
+
+use super::{stage_stack::{Stage, StageAction, StageMessage}, resources::Resources, button::{Button, clicked_button}};
+
+pub struct ConfirmStage {
+  camera: Camera2D,
+  question: &'static str,
+  message: StageMessage,
+  bindings: Bindings,
+}
+
+impl ConfirmStage {
+  pub fn new(question: &'static str, message: StageMessage) -> Self {
+    Self {
+      camera: Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height())),
+      question,
+      message,
+      bindings: Bindings::load_or_default(),
+    }
+  }
+
+  fn get_buttons(&self) -> Vec<Button<bool>> {
+    Button::row(&[("Yes", true), ("No", false)], screen_width() / 2., screen_height() / 2.)
+  }
+}
+
+impl Stage for ConfirmStage {
+  fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+    let answer = clicked_button(&self.get_buttons());
+    if answer == Some(true) || self.bindings.is_pressed(Action::Confirm) {
+      return Some(StageAction::PopWith(self.message));
+    }
+
+    if answer == Some(false) || self.bindings.is_pressed(Action::Back) {
+      return Some(StageAction::Pop);
+    }
+
+    None
+  }
+
+  fn draw(&self, _resources: &Resources) {
+    set_camera(&self.camera);
+
+    let buttons = self.get_buttons();
+    let bottom = buttons[0].rect.bottom() + 16.;
+    draw_rectangle(0., screen_height() / 2. - 60., screen_width(), bottom - (screen_height() / 2. - 60.), Color::from_rgba(0, 0, 0, 200));
+    let size = measure_text(self.question, None, 24, 1.);
+    draw_text(self.question, (screen_width() - size.width) / 2., screen_height() / 2. - 20., 24., WHITE);
+    for button in buttons {
+      button.draw();
+    }
+  }
+
+  fn get_name(&self) -> &'static str {
+    "confirm"
+  }
+
+  fn is_transparent(&self) -> bool {
+    true
+  }
+}
@@ -0,0 +1,92 @@
+use macroquad::{prelude::*, ui::root_ui};
+
+use crate::input::{Action, Bindings, Trigger, ACTIONS, BINDINGS_PATH, CANCEL_KEY};
+
+use super::{stage_stack::{Stage, StageAction}, resources::Resources};
+
+const ROW_HEIGHT: f32 = 22.;
+
+pub struct ControlsStage {
+  camera: Camera2D,
+  bindings: Bindings,
+  rebinding: Option<Action>,
+}
+
+impl ControlsStage {
+  pub fn new() -> Self {
+    Self {
+      camera: Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height())),
+      bindings: Bindings::load_or_default(),
+      rebinding: None,
+    }
+  }
+
+  fn save(&self) {
+    if let Err(e) = self.bindings.save(BINDINGS_PATH) {
+      warn!("controls could not be saved: {}", e);
+    }
+  }
+}
+
+impl Stage for ControlsStage {
+  fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+    if let Some(action) = self.rebinding {
+      if is_key_pressed(CANCEL_KEY) {
+        self.rebinding = None;
+      } else if let Some(trigger) = Trigger::get_pressed() {
+        self.bindings.add(action, trigger);
+        self.rebinding = None;
+        self.save();
+      }
+      return None;
+    }
+
+    let x = screen_width() / 2. - 120.;
+    for (index, action) in ACTIONS.iter().enumerate() {
+      let triggers: Vec<String> = self.bindings.get(*action).iter().map(|t| t.get_name()).collect();
+      let label = format!("{}: {}", action.get_name(), triggers.join(", "));
+      let y = 60. + index as f32 * ROW_HEIGHT;
+      if root_ui().button(Some(Vec2::new(x - 25., y)), "x") {
+        self.bindings.set(*action, vec![]);
+        self.save();
+      }
+      if root_ui().button(Some(Vec2::new(x, y)), label.as_str()) {
+        self.rebinding = Some(*action);
+        return None;
+      }
+    }
+
+    let y = 70. + ACTIONS.len() as f32 * ROW_HEIGHT;
+    if root_ui().button(Some(Vec2::new(x, y)), "Reset defaults") {
+      self.bindings = Bindings::default();
+      self.save();
+    }
+
+    if root_ui().button(Some(Vec2::new(x, y + 25.)), "Back") || self.bindings.is_pressed(Action::Back) {
+      return Some(StageAction::Pop);
+    }
+
+    None
+  }
+
+  fn draw(&self, _resources: &Resources) {
+    set_camera(&self.camera);
+
+    draw_rectangle(0., 0., screen_width(), screen_height(), Color::from_rgba(0, 0, 0, 220));
+    draw_text("CONTROLS", screen_width() / 2. - 70., 40., 40., WHITE);
+    if let Some(action) = self.rebinding {
+      let text = format!("Press a key, mouse button, touch or gamepad button to add to {}, Escape to cancel", action.get_name());
+      let size = measure_text(&text, None, 24, 1.);
+      draw_rectangle(0., screen_height() / 2. - 30., screen_width(), 50., Color::from_rgba(0, 0, 0, 240));
+      draw_text(&text, (screen_width() - size.width) / 2., screen_height() / 2., 24., YELLOW);
+    }
+  }
+
+  fn get_name(&self) -> &'static str {
+    "controls"
+  }
+
+  fn is_transparent(&self) -> bool {
+    true
+  }
+}
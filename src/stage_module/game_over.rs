@@ -1,39 +1,107 @@
-use macroquad::{prelude::*, ui::root_ui};
+use macroquad::prelude::*;
 
-use super::stage_stack::{Stage, StageAction};
+use crate::{systems::rng::generate_seed, highscore::HighScoreTable, display::draw_high_scores, world_module::stats::RunStats};
+
+use super::{stage_stack::{Stage, StageAction}, resources::Resources, playing::PlayingStage, button::{Button, clicked_button, get_button_size}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GameOverAction {
+  Again,
+  RetrySeed,
+  MainMenu,
+}
 
 pub struct GameOver {
   camera: Camera2D,
   score: usize,
+  seed: u64,
+  high_scores: HighScoreTable,
+  rank: Option<usize>,
+  stats: Option<RunStats>,
 }
 
 impl GameOver {
-  pub fn new(score: usize) -> Self {
+  pub fn new(score: usize, seed: u64) -> Self {
     Self {
       score,
+      seed,
+      high_scores: HighScoreTable::default(),
+      rank: None,
+      stats: None,
       camera: Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height()))
     }
   }
-}
 
-impl Stage for GameOver {
-  fn update(&mut self, _resources: &super::resources::Resources) -> Option<StageAction> {
-    if root_ui().button(Some(Vec2::new(screen_width() / 2. - 24., screen_height() / 2. - 50.)), "Again") {
-      return Some(StageAction::StartGame);
-    }
+  pub fn with_high_scores(mut self, high_scores: HighScoreTable, rank: Option<usize>) -> Self {
+    self.high_scores = high_scores;
+    self.rank = rank;
+    self
+  }
+
+  pub fn with_stats(mut self, stats: RunStats) -> Self {
+    self.stats = Some(stats);
+    self
+  }
+
+  fn get_buttons(&self) -> Vec<Button<GameOverAction>> {
+    let top = screen_height() - get_button_size().y - 8.;
+    let items = [("Again", GameOverAction::Again), ("Retry seed", GameOverAction::RetrySeed), ("Main menu", GameOverAction::MainMenu)];
+    Button::row(&items, screen_width() / 2., top)
+  }
 
-    if root_ui().button(Some(Vec2::new(screen_width() / 2. - 100., screen_height() / 2. + 50.)), "Back to main menu") {
-      return Some(StageAction::EndGame);
+  fn draw_stats(&self, left: f32, top: f32) {
+    let stats = match &self.stats {
+      Some(stats) => stats,
+      None => return,
+    };
+
+    let mut lines = vec![
+      format!("Time survived: {:.1}s", stats.time),
+      format!("Max difficulty: {}", stats.max_difficulty),
+      format!("Distance walked: {:.0}", stats.distance_walked),
+      format!("Projectiles: {} fired, {} hits ({:.0}%)", stats.projectiles_fired, stats.projectiles_hit, stats.get_accuracy() * 100.),
+      format!("Kills: {}", stats.get_total_kills()),
+    ];
+    lines.extend(stats.kills.iter().map(|(source, count)| format!("  {}: {}", source.get_name(), count)));
+    lines.push(format!("Damage taken: {}", stats.damage_taken.iter().map(|(_, amount)| amount).sum::<isize>()));
+    lines.extend(stats.damage_taken.iter().map(|(source, amount)| format!("  {}: {}", source.get_name(), amount)));
+
+    for (index, line) in lines.iter().enumerate() {
+      draw_text(line.as_str(), left, top + index as f32 * 18., 18., WHITE);
     }
+  }
+}
 
-    None
+impl Stage for GameOver {
+  fn update(&mut self, resources: &Resources) -> Option<StageAction> {
+    match clicked_button(&self.get_buttons()) {
+      Some(GameOverAction::Again) => Some(StageAction::Replace(Box::new(PlayingStage::new(resources, generate_seed())))),
+      Some(GameOverAction::RetrySeed) => Some(StageAction::Replace(Box::new(PlayingStage::new(resources, self.seed)))),
+      Some(GameOverAction::MainMenu) => Some(StageAction::PopTo("main_menu")),
+      None => None,
+    }
   }
 
-  fn draw(&self, _resources: &super::resources::Resources) {
+  fn draw(&self, _resources: &Resources) {
     clear_background(DARKGRAY);
 
     set_camera(&self.camera);
 
     draw_text(format!("Score: {}", self.score).as_str(), 5., 60., 50., WHITE);
+    draw_text(format!("Seed: {}", self.seed).as_str(), 5., 100., 30., WHITE);
+    match self.rank {
+      Some(0) => draw_text("NEW RECORD!", 5., 140., 30., YELLOW),
+      Some(rank) => draw_text(format!("New high score, rank {}", rank + 1).as_str(), 5., 140., 30., YELLOW),
+      None => (),
+    }
+    self.draw_stats(screen_width() - 260., 30.);
+    draw_high_scores(5., 170., &self.high_scores, self.rank);
+    for button in self.get_buttons() {
+      button.draw();
+    }
+  }
+
+  fn get_name(&self) -> &'static str {
+    "game_over"
   }
 }
@@ -0,0 +1,61 @@
+use macroquad::prelude::*;
+
+use crate::{upgrade::Upgrade, input::{Action, Bindings}};
+
+use super::{stage_stack::{Stage, StageAction, StageMessage}, resources::Resources, button::{Button, clicked_button}};
+
+const CHOICE_ACTIONS: [Action; 3] = [Action::Choice1, Action::Choice2, Action::Choice3];
+
+pub struct LevelUpStage {
+  camera: Camera2D,
+  choices: Vec<Upgrade>,
+  bindings: Bindings,
+}
+
+impl LevelUpStage {
+  pub fn new(choices: Vec<Upgrade>) -> Self {
+    Self {
+      choices,
+      bindings: Bindings::load_or_default(),
+      camera: Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height())),
+    }
+  }
+
+  fn get_buttons(&self) -> Vec<Button<usize>> {
+    let items: Vec<(String, usize)> = self.choices
+      .iter()
+      .enumerate()
+      .map(|(index, upgrade)| (format!("{}. {}", index + 1, upgrade.get_description()), index))
+      .collect();
+    Button::column(&items, screen_width() / 2., (screen_height() - Button::<usize>::get_column_height(items.len())) / 2.)
+  }
+}
+
+impl Stage for LevelUpStage {
+  fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+    let key_pressed = (0..self.choices.len()).find(|index| CHOICE_ACTIONS.get(*index).map(|action| self.bindings.is_pressed(*action)).unwrap_or(false));
+    clicked_button(&self.get_buttons())
+      .or(key_pressed)
+      .map(|index| StageAction::PopWith(StageMessage::UpgradeChosen(index)))
+  }
+
+  fn draw(&self, _resources: &Resources) {
+    set_camera(&self.camera);
+
+    draw_rectangle(0., 0., screen_width(), screen_height(), Color::from_rgba(0, 0, 0, 160));
+    let buttons = self.get_buttons();
+    let top = buttons.first().map_or(screen_height() / 2., |button| button.rect.y);
+    draw_text("LEVEL UP", screen_width() / 2. - 70., top - 16., 40., WHITE);
+    for button in buttons {
+      button.draw();
+    }
+  }
+
+  fn get_name(&self) -> &'static str {
+    "level_up"
+  }
+
+  fn is_transparent(&self) -> bool {
+    true
+  }
+}
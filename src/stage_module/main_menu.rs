@@ -1,27 +1,84 @@
-use macroquad::{prelude::*, ui::root_ui};
+use macroquad::prelude::*;
 
-use super::stage_stack::{Stage, StageAction};
+use crate::{highscore::HighScoreTable, display::draw_high_scores, systems::rng::{generate_seed, daily_seed}, snapshot::{self, SNAPSHOT_PATH}, storage, replay::{Replay, REPLAY_PATH}};
 
-pub struct MainMenu {}
+use super::{stage_stack::{Stage, StageAction, StageMessage}, resources::Resources, playing::PlayingStage, replay::ReplayStage, button::{Button, clicked_button}};
 
-impl Stage for MainMenu {
-  fn update(&mut self, _resources: &super::resources::Resources) -> Option<StageAction> {
-    if root_ui().button(Some(Vec2::new(screen_width() / 2. - 24., screen_height() / 2. - 50.)), "Start") {
-      return Some(StageAction::StartGame);
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MenuAction {
+  Continue,
+  Start,
+  DailyChallenge,
+  WatchReplay,
+  Quit,
+}
+
+pub struct MainMenu {
+  high_scores: HighScoreTable,
+}
+
+impl MainMenu {
+  pub fn new() -> Self {
+    Self {
+      high_scores: HighScoreTable::load_or_default(),
     }
+  }
 
-    if root_ui().button(Some(Vec2::new(screen_width() / 2. - 20., screen_height() / 2. + 50.)), "Quit") {
-      return Some(StageAction::GameQuit);
+  fn get_buttons(&self) -> Vec<Button<MenuAction>> {
+    let mut items = vec![];
+    if storage::exists(SNAPSHOT_PATH) {
+      items.push(("Continue", MenuAction::Continue));
     }
+    items.extend([
+      ("Start", MenuAction::Start),
+      ("Daily challenge", MenuAction::DailyChallenge),
+      ("Watch replay", MenuAction::WatchReplay),
+      ("Quit", MenuAction::Quit),
+    ]);
+    Button::column(&items, screen_width() * 0.22, 80.)
+  }
+}
 
-    None
+impl Stage for MainMenu {
+  fn update(&mut self, resources: &Resources) -> Option<StageAction> {
+    match clicked_button(&self.get_buttons()) {
+      Some(MenuAction::Continue) => match snapshot::load_from_file(SNAPSHOT_PATH) {
+        Ok(simulation) => Some(StageAction::Push(Box::new(PlayingStage::from_simulation(resources, simulation)))),
+        Err(e) => {
+          warn!("saved game could not be loaded: {}", e);
+          None
+        },
+      },
+      Some(MenuAction::Start) => Some(StageAction::Push(Box::new(PlayingStage::new(resources, generate_seed())))),
+      Some(MenuAction::DailyChallenge) => Some(StageAction::Push(Box::new(PlayingStage::new(resources, daily_seed())))),
+      Some(MenuAction::WatchReplay) => match Replay::load(REPLAY_PATH).and_then(|replay| replay.check_tables(resources.tables_hash).map(|_| replay)) {
+        Ok(replay) => Some(StageAction::Push(Box::new(ReplayStage::new(resources, replay)))),
+        Err(e) => {
+          warn!("replay could not be loaded: {}", e);
+          None
+        },
+      },
+      Some(MenuAction::Quit) => Some(StageAction::GameQuit),
+      None => None,
+    }
   }
 
-  fn draw(&self, _resources: &super::resources::Resources) {
+  fn draw(&self, _resources: &Resources) {
     clear_background(DARKGRAY);
+    set_default_camera();
 
-    root_ui().label(Some(Vec2::new(screen_width() / 2.- 20., screen_height() / 2. - 100.)), "kg-g");
+    draw_text("kg-g", screen_width() * 0.22 - 40., 56., 48., WHITE);
+    for button in self.get_buttons() {
+      button.draw();
+    }
+    draw_high_scores(screen_width() * 0.42, 100., &self.high_scores, None);
   }
-}
 
+  fn on_resume(&mut self, _message: Option<StageMessage>) {
+    self.high_scores = HighScoreTable::load_or_default();
+  }
 
+  fn get_name(&self) -> &'static str {
+    "main_menu"
+  }
+}
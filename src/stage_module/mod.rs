@@ -2,4 +2,11 @@ pub mod stage_stack;
 pub mod playing;
 pub mod main_menu;
 pub mod resources;
-pub mod game_over;
\ No newline at end of file
+pub mod game_over;
+pub mod replay;
+pub mod level_up;
+pub mod pause;
+pub mod confirm;
+pub mod settings;
+pub mod button;
+pub mod controls;
\ No newline at end of file
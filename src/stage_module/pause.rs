@@ -0,0 +1,84 @@
+use macroquad::prelude::*;
+
+use crate::input::{Action, Bindings};
+
+use super::{stage_stack::{Stage, StageAction, StageMessage}, resources::Resources, confirm::ConfirmStage, settings::SettingsStage, button::{Button, clicked_button}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PauseAction {
+  Resume,
+  Restart,
+  Settings,
+  QuitToMenu,
+}
+
+pub struct PauseStage {
+  camera: Camera2D,
+  requested: Option<StageMessage>,
+  bindings: Bindings,
+}
+
+impl PauseStage {
+  pub fn new() -> Self {
+    Self {
+      camera: Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height())),
+      requested: None,
+      bindings: Bindings::load_or_default(),
+    }
+  }
+
+  fn get_buttons(&self) -> Vec<Button<PauseAction>> {
+    let items = [
+      ("Resume", PauseAction::Resume),
+      ("Restart", PauseAction::Restart),
+      ("Settings", PauseAction::Settings),
+      ("Quit to menu", PauseAction::QuitToMenu),
+    ];
+    Button::column(&items, screen_width() / 2., (screen_height() - Button::<PauseAction>::get_column_height(items.len())) / 2.)
+  }
+}
+
+impl Stage for PauseStage {
+  fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+    if let Some(message) = self.requested.take() {
+      return Some(StageAction::PopWith(message));
+    }
+
+    if self.bindings.is_pressed(Action::Pause) || self.bindings.is_pressed(Action::Back) {
+      return Some(StageAction::Pop);
+    }
+
+    match clicked_button(&self.get_buttons()) {
+      Some(PauseAction::Resume) => Some(StageAction::Pop),
+      Some(PauseAction::Restart) =>
+        Some(StageAction::Push(Box::new(ConfirmStage::new("Abandon this run and restart?", StageMessage::Restart)))),
+      Some(PauseAction::Settings) => Some(StageAction::Push(Box::new(SettingsStage::new()))),
+      Some(PauseAction::QuitToMenu) =>
+        Some(StageAction::Push(Box::new(ConfirmStage::new("Leave this run and go to the menu?", StageMessage::QuitToMenu)))),
+      None => None,
+    }
+  }
+
+  fn draw(&self, _resources: &Resources) {
+    set_camera(&self.camera);
+
+    draw_rectangle(0., 0., screen_width(), screen_height(), Color::from_rgba(0, 0, 0, 160));
+    let buttons = self.get_buttons();
+    draw_text("PAUSED", screen_width() / 2. - 56., buttons[0].rect.y - 16., 40., WHITE);
+    for button in buttons {
+      button.draw();
+    }
+  }
+
+  fn get_name(&self) -> &'static str {
+    "pause"
+  }
+
+  fn is_transparent(&self) -> bool {
+    true
+  }
+
+  fn on_resume(&mut self, message: Option<StageMessage>) {
+    self.requested = message;
+  }
+}
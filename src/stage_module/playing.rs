@@ -1,45 +1,63 @@
-use macroquad::{prelude::*, rand::ChooseRandom};
+use macroquad::prelude::*;
 
-use crate::{world_module::{world::{World}, actor::Actor}, player::Player, systems::{ai::{Ai, WeightedStates}, timer::Timer}, display::Renderer};
+use crate::{simulation::Simulation, display::Renderer, replay::{Replay, REPLAY_PATH}, systems::timer::Timer, snapshot::{self, SNAPSHOT_PATH}, storage, lifecycle, settings::Settings, input::{Action, Bindings, InputEvent, VirtualJoystick}, highscore::{HighScore, HighScoreTable, HIGH_SCORES_PATH}};
 
-use super::{stage_stack::{Stage, StageAction}, resources::Resources};
+use super::{stage_stack::{Stage, StageAction, StageMessage}, resources::Resources, game_over::GameOver, level_up::LevelUpStage, pause::PauseStage, button::{Button, get_button_size}};
 
-const ENEMIES_COUNT: usize = 0;
-const BASE_SPAWN_TRESHOLD: f32 = 5.;
+const AUTOSAVE_INTERVAL: f32 = 5.;
 
 pub struct PlayingStage {
-  world: World,
-  difficulty: usize,
-  paused: bool,
+  simulation: Simulation,
+  replay: Option<Replay>,
+  pending_inputs: Vec<InputEvent>,
+  autosave_timer: Timer,
+  settings: Settings,
+  bindings: Bindings,
+  steering: Vec2,
+  joystick: VirtualJoystick,
+  requested: Option<StageMessage>,
   renderer: Renderer,
   camera: Camera2D,
-  spawn_timer: Timer,
-  difficulty_timer: Timer,
 }
 
 impl PlayingStage {
-  pub fn new(resources: &Resources) -> Self {
-    let player_position = Vec2::new(0., 0.);
-    let player_actor = Actor::new(player_position, 100., 5);
-    let player = Player::new(player_actor, 1.);
-
-    let mut ai_actors: Vec<(Actor, Ai)> = vec![];
-    for c in 0..ENEMIES_COUNT {
-      let x_mod = (c % 12) as f32;
-      let y_mod = (c / 12) as f32;
-      let actor = Actor::new(Vec2::new(32. + x_mod * 64., 64. + y_mod * 64.), 80., 2);
-      let ai = Ai::new(WeightedStates::new_idle_wandering(&[1, 5, 30]));
-      ai_actors.push((actor, ai));
+  pub fn new(resources: &Resources, seed: u64) -> Self {
+    let settings = Settings::load_or_default();
+    Self {
+      simulation: Simulation::new(resources.viewport, seed)
+        .with_enemy_table(resources.enemies.clone())
+        .with_wave_script(resources.waves.clone())
+        .with_drop_table(resources.drops.clone()),
+      replay: Some(Replay::new(seed, resources.viewport, resources.tables_hash)),
+      pending_inputs: vec![],
+      autosave_timer: Timer::new(AUTOSAVE_INTERVAL),
+      renderer: Renderer { debug: settings.show_debug },
+      settings,
+      bindings: Bindings::load_or_create(),
+      steering: Vec2::ZERO,
+      joystick: VirtualJoystick::default(),
+      requested: None,
+      camera: resources.get_camera(),
     }
+  }
 
+  pub fn from_simulation(resources: &Resources, simulation: Simulation) -> Self {
+    let settings = Settings::load_or_default();
     Self {
-      difficulty: 0,
-      world: World::new(player).with_ai_actors(ai_actors),
-      paused: false,
-      renderer: Renderer { debug: false },
+      simulation: simulation
+        .with_enemy_table(resources.enemies.clone())
+        .with_wave_script(resources.waves.clone())
+        .with_drop_table(resources.drops.clone()),
+      replay: None,
+      pending_inputs: vec![],
+      autosave_timer: Timer::new(AUTOSAVE_INTERVAL),
+      renderer: Renderer { debug: settings.show_debug },
+      settings,
+      bindings: Bindings::load_or_create(),
+      steering: Vec2::ZERO,
+      joystick: VirtualJoystick::default(),
+      requested: None,
       camera: resources.get_camera(),
-      spawn_timer: Timer::new(2.),
-      difficulty_timer: Timer::new(BASE_SPAWN_TRESHOLD),
     }
   }
 }
@@ -53,73 +71,115 @@ impl PlayingStage {
       self.camera.target.y + (resources.viewport.1 / 2.),
     )
   }
+
+  fn save_replay(&self) {
+    if let Some(replay) = &self.replay {
+      if let Err(e) = replay.save(REPLAY_PATH) {
+        warn!("replay could not be saved: {}", e);
+      }
+    }
+  }
+
+  fn record_high_score(&self) -> (HighScoreTable, Option<usize>) {
+    let mut high_scores = HighScoreTable::load_or_default();
+    let rank = high_scores.insert(HighScore::from_simulation(&self.simulation));
+    if rank.is_some() {
+      if let Err(e) = high_scores.save(HIGH_SCORES_PATH) {
+        warn!("high scores could not be saved: {}", e);
+      }
+    }
+    (high_scores, rank)
+  }
+
+  // Square pause control in the top right corner, for touch screens without a pause key.
+  fn get_pause_button(&self) -> Button<()> {
+    let size = get_button_size().y;
+    Button::new(Rect::new(screen_width() - size - 8., 8., size, size), "II", ())
+  }
+
+  fn pause(&self) -> Option<StageAction> {
+    self.save_snapshot();
+    Some(StageAction::Push(Box::new(PauseStage::new())))
+  }
+
+  fn save_snapshot(&self) {
+    if let Err(e) = snapshot::save_to_file(SNAPSHOT_PATH, &self.simulation) {
+      warn!("game could not be saved: {}", e);
+    }
+  }
 }
 
 impl Stage for PlayingStage {
   fn update(&mut self, resources: &Resources) -> Option<StageAction> {
-    self.camera.target = self.world.player.actor.movable.position;
+    self.camera.target = self.simulation.world.player.actor.movable.position;
+
+    match self.requested.take() {
+      Some(StageMessage::Restart) => {
+        storage::remove(SNAPSHOT_PATH);
+        return Some(StageAction::Replace(Box::new(PlayingStage::new(resources, self.simulation.get_seed()))));
+      },
+      Some(StageMessage::QuitToMenu) => {
+        self.save_replay();
+        self.save_snapshot();
+        return Some(StageAction::PopTo("main_menu"));
+      },
+      _ => (),
+    }
 
-    if is_key_pressed(KeyCode::D) {
+    if self.bindings.is_pressed(Action::ToggleDebug) {
       self.renderer.debug = !self.renderer.debug;
     }
 
-    if is_key_pressed(KeyCode::P) {
-      self.paused = !self.paused;
+    let pause_button = self.get_pause_button();
+    if self.bindings.is_pressed(Action::Pause) || self.bindings.is_pressed(Action::Back) || pause_button.is_clicked() {
+      return self.pause();
     }
 
-    if is_key_pressed(KeyCode::Escape) {
-      return Some(StageAction::EndGame);
+    if lifecycle::is_focus_lost() {
+      if self.settings.auto_pause {
+        return self.pause();
+      }
+      self.save_snapshot();
     }
 
-    if !self.world.player.actor.is_alive() {
-      return Some(StageAction::GameOver(self.world.score));
+    if self.simulation.is_over() {
+      self.save_replay();
+      storage::remove(SNAPSHOT_PATH);
+      let (high_scores, rank) = self.record_high_score();
+      let game_over = GameOver::new(self.simulation.world.score, self.simulation.get_seed())
+        .with_high_scores(high_scores, rank)
+        .with_stats(self.simulation.world.get_run_stats());
+      return Some(StageAction::Replace(Box::new(game_over)));
     }
 
-    if !self.paused {
-      let delta_t = get_frame_time();
-      if is_mouse_button_pressed(MouseButton::Left) {
-        let m_vec = self.camera.screen_to_world(Vec2::from(mouse_position()));
-        if self.world.bounds.contains(m_vec) {
-          self.world.on_mouse_button_down(m_vec);
-        }
-      }
+    if self.simulation.is_waiting_for_upgrade() && self.pending_inputs.is_empty() {
+      return Some(StageAction::Push(Box::new(LevelUpStage::new(self.simulation.get_upgrade_choices().clone()))));
+    }
 
-      self.world.update(delta_t);
-
-      let (vp_left, vp_right, vp_top, vp_bottom) = self.get_lrtb(resources);
-      let (left, right, top, bottom) = (vp_left.max(self.world.bounds.left()), vp_right.min(self.world.bounds.right()), vp_top.max(self.world.bounds.top()), vp_bottom.min(self.world.bounds.bottom()));
-
-      self.spawn_timer.update(delta_t);
-      self.difficulty_timer.update(delta_t);
-
-      if self.spawn_timer.is_just_over() {
-        if let Some(pos) = vec![
-          Vec2::new(left, rand::gen_range::<f32>(top, bottom)),
-          Vec2::new(right, rand::gen_range::<f32>(top, bottom)),
-          Vec2::new(rand::gen_range::<f32>(left, right), top),
-          Vec2::new(rand::gen_range::<f32>(left, right), bottom),
-        ].choose() {
-          let actor = Actor::new(*pos, 70. + self.difficulty as f32, 2);
-          let ai = Ai::new(WeightedStates::new_idle_wandering(&[1, 5, 7+self.difficulty as i32]));
-          self.world.add_ai_actor(actor, ai);
-        }
-      }
+    let mut inputs: Vec<InputEvent> = self.pending_inputs.drain(..).collect();
+    if self.settings.virtual_joystick {
+      self.joystick.update(&touches());
+    }
+    let presses = self.bindings.get_pointer_presses(Action::MoveTo);
+    for press in presses.iter().filter(|p| !self.joystick.is_holding(p.touch_id) && !pause_button.rect.contains(p.position)) {
+      inputs.push(InputEvent::MoveTo(self.camera.screen_to_world(press.position)));
+    }
 
-      if self.difficulty_timer.is_just_over() {
-        self.difficulty += 1;
-
-        let spawn_treshold = match self.difficulty {
-          1 => 4.,
-          2 => 3.,
-          3..=4 => 2.,
-          5..=7 => 1.,
-          8..=11 => 0.9,
-          12..=16 => 0.7,
-          _ => 0.5,
-        };
-        self.spawn_timer.set_treshold(spawn_treshold);
-        self.spawn_timer.reset();
-      }
+    let steering = if self.joystick.is_active() { self.joystick.get_direction() } else { self.bindings.get_movement_direction() };
+    if steering != self.steering {
+      inputs.push(InputEvent::Steer(steering));
+      self.steering = steering;
+    }
+
+    let delta_t = get_frame_time();
+    if let Some(replay) = &mut self.replay {
+      replay.record(delta_t, &inputs);
+    }
+    self.simulation.update_with_inputs(delta_t, &inputs);
+
+    self.autosave_timer.update(delta_t);
+    if self.autosave_timer.is_just_over() {
+      self.save_snapshot();
     }
 
     None
@@ -128,29 +188,30 @@ impl Stage for PlayingStage {
   fn draw(&self, resources: &Resources) {
     clear_background(BLACK);
 
-    draw_rectangle(self.world.bounds.x, self.world.bounds.x, self.world.bounds.w, self.world.bounds.h, DARKGRAY);
-
     set_camera(&self.camera);
 
-    if self.paused {
-      draw_text("PAUSED", screen_width() / 2. - 40., screen_height() / 2. - 4., 32., WHITE);
-    }
-
-    let player_texure = if self.world.player.invlunerable { &resources.texture_actor_flashing } else { &resources.texture_actor };
-    self.renderer.draw_actor(player_texure, &self.world.get_player().actor);
-    for actor in self.world.get_ai_actors() {
-      self.renderer.draw_actor(&resources.texture_enemy, actor);
-    }
-    for projectile in self.world.get_projectiles() {
-      self.renderer.draw_projectile(&resources.texture_fireball, projectile);
-    }
-    for particle in self.world.get_particles() {
-      self.renderer.draw_particle(&resources.texture_fireball, particle);
-    }
+    self.renderer.draw_world(resources, &self.simulation.world);
 
     let (left, _r, top, _b) = self.get_lrtb(resources);
 
-    self.renderer.draw_player_info(left, top, &self.world, self.difficulty);
-    self.renderer.draw_debug(left, top, &self.world);
+    self.renderer.draw_player_info(left, top, &self.simulation.world, self.simulation.difficulty, self.simulation.get_wave_name());
+    self.renderer.draw_debug(left, top, &self.simulation.world);
+
+    set_default_camera();
+    self.joystick.draw();
+    self.get_pause_button().draw();
   }
-}
\ No newline at end of file
+
+  fn get_name(&self) -> &'static str {
+    "playing"
+  }
+
+  fn on_resume(&mut self, message: Option<StageMessage>) {
+    self.settings = Settings::load_or_default();
+    self.bindings = Bindings::load_or_default();
+    match message {
+      Some(StageMessage::UpgradeChosen(index)) => self.pending_inputs.push(InputEvent::ChooseUpgrade(index)),
+      message => self.requested = message,
+    }
+  }
+}
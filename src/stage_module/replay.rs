@@ -0,0 +1,100 @@
+use macroquad::prelude::*;
+
+use crate::{simulation::Simulation, display::Renderer, replay::Replay, input::{Action, Bindings}};
+
+use super::{stage_stack::{Stage, StageAction}, resources::Resources};
+
+pub struct ReplayStage {
+  replay: Replay,
+  simulation: Simulation,
+  frame: usize,
+  paused: bool,
+  bindings: Bindings,
+  renderer: Renderer,
+  camera: Camera2D,
+}
+
+impl ReplayStage {
+  pub fn new(resources: &Resources, replay: Replay) -> Self {
+    Self {
+      simulation: replay.create_simulation()
+        .with_enemy_table(resources.enemies.clone())
+        .with_wave_script(resources.waves.clone())
+        .with_drop_table(resources.drops.clone()),
+      replay,
+      frame: 0,
+      paused: false,
+      bindings: Bindings::load_or_default(),
+      renderer: Renderer { debug: false },
+      camera: resources.get_camera(),
+    }
+  }
+
+  fn is_finished(&self) -> bool {
+    self.frame >= self.replay.frames.len()
+  }
+
+  fn step(&mut self) {
+    if let Some(record) = self.replay.frames.get(self.frame) {
+      self.simulation.update_with_inputs(record.delta_t, &record.inputs);
+      self.frame += 1;
+    }
+  }
+
+  fn get_lt(&self, resources: &Resources) -> (f32, f32) {
+    (
+      self.camera.target.x - (resources.viewport.0 / 2.),
+      self.camera.target.y - (resources.viewport.1 / 2.),
+    )
+  }
+}
+
+impl Stage for ReplayStage {
+  fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+    self.camera.target = self.simulation.world.player.actor.movable.position;
+
+    if self.bindings.is_pressed(Action::ToggleDebug) {
+      self.renderer.debug = !self.renderer.debug;
+    }
+
+    if self.bindings.is_pressed(Action::Pause) {
+      self.paused = !self.paused;
+    }
+
+    if self.bindings.is_pressed(Action::Back) {
+      return Some(StageAction::Pop);
+    }
+
+    if !self.paused || self.bindings.is_pressed(Action::Step) {
+      self.step();
+    }
+
+    None
+  }
+
+  fn draw(&self, resources: &Resources) {
+    clear_background(BLACK);
+
+    set_camera(&self.camera);
+
+    self.renderer.draw_world(resources, &self.simulation.world);
+
+    let (left, top) = self.get_lt(resources);
+
+    self.renderer.draw_player_info(left, top, &self.simulation.world, self.simulation.difficulty, self.simulation.get_wave_name());
+    self.renderer.draw_debug(left, top, &self.simulation.world);
+
+    let status = if self.is_finished() { "REPLAY FINISHED" } else if self.paused { "REPLAY PAUSED" } else { "REPLAY" };
+    draw_text(
+      format!("{} {}/{}", status, self.frame, self.replay.frames.len()).as_str(),
+      left + 4.,
+      top + resources.viewport.1 - 12.,
+      24.,
+      WHITE
+    );
+  }
+
+  fn get_name(&self) -> &'static str {
+    "replay"
+  }
+}
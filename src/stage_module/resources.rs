@@ -1,16 +1,28 @@
 use macroquad::prelude::*;
-use crate::utils::{ReplaceColors, customize_image};
+use crate::{utils::{ReplaceColors, customize_image}, world_module::{enemy::EnemyTable, pickup::DropTable}, director::WaveScript, palette::PaletteShader};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+  Player,
+  Flash,
+  Enemy(usize),
+}
 
 pub struct Resources {
-  pub texture_actor: Texture2D,
-  pub texture_actor_flashing: Texture2D,
-  pub texture_enemy: Texture2D,
-  pub texture_fireball: Texture2D,
+  palettes: Vec<ReplaceColors>,
+  palette_shader: Option<PaletteShader>,
+  texture_palette_base: Texture2D,
+  textures_palette: Vec<Texture2D>,
+  pub texture_sheet: Texture2D,
+  pub enemies: EnemyTable,
+  pub waves: WaveScript,
+  pub drops: DropTable,
+  pub tables_hash: u64,
   pub viewport: (f32, f32)
 }
 
 impl Resources {
-  pub fn new(image: Image) -> Self {
+  pub fn new(image: Image, enemies: EnemyTable, waves: WaveScript, drops: DropTable, tables_hash: u64) -> Self {
     let colors_flashing = ReplaceColors::new(
       Color::from_rgba(255, 255, 255, 255),
         Color::from_rgba(255, 255, 255, 255),
@@ -25,17 +37,19 @@ impl Resources {
         Color::from_rgba(0, 0, 0, 255),
     );
 
-    let colors_enemy = ReplaceColors::new(
-        Color::from_rgba(57, 159, 50, 255),
-        Color::from_rgba(66, 105, 29, 255),
-        Color::from_rgba(66, 159, 50, 255),
-        Color::from_rgba(0, 0, 0, 255),
-    );
+    let mut palettes = vec![colors_actor, colors_flashing];
+    palettes.extend(enemies.archetypes.iter().map(|archetype| archetype.get_colors()));
 
-    let texture_actor = customize_image(image.sub_image(Rect::new(0., 0., 16. * 3., 16.)), colors_actor);
-    let texture_actor_flashing = customize_image(image.sub_image(Rect::new(0., 0., 16. * 3., 16.)), colors_flashing);
-    let texture_enemy = customize_image(image.sub_image(Rect::new(16. * 3., 0., 16. * 3., 16.)), colors_enemy);
-    let texture_fireball =  Texture2D::from_image(&image.sub_image(Rect::new(16. * 6., 0., 16. * 4., 16.)));
+    let palette_shader = PaletteShader::load()
+      .map_err(|e| warn!("palette shader not available, recoloring textures on the cpu: {}", e))
+      .ok();
+    let texture_palette_base = Texture2D::from_image(&image);
+    texture_palette_base.set_filter(FilterMode::Nearest);
+    let textures_palette = match palette_shader {
+      Some(_) => vec![],
+      None => palettes.iter().map(|colors| customize_image(image.clone(), colors)).collect(),
+    };
+    let texture_sheet = Texture2D::from_image(&image);
 
     let ratio = screen_width() / screen_height();
     let mut i = 1.;
@@ -54,10 +68,42 @@ impl Resources {
 
     Self {
       viewport: (i, i / ratio),
-      texture_actor,
-      texture_actor_flashing,
-      texture_enemy,
-      texture_fireball,
+      palettes,
+      palette_shader,
+      texture_palette_base,
+      textures_palette,
+      texture_sheet,
+      enemies,
+      waves,
+      drops,
+      tables_hash,
+    }
+  }
+
+  fn get_palette_index(&self, palette: Palette) -> usize {
+    match palette {
+      Palette::Player => 0,
+      Palette::Flash => 1,
+      Palette::Enemy(kind) if kind + 2 < self.palettes.len() => kind + 2,
+      Palette::Enemy(_) => 2,
+    }
+  }
+
+  // returns the texture actors should be drawn with, call reset_palette once done
+  pub fn use_palette(&self, palette: Palette) -> Texture2D {
+    let index = self.get_palette_index(palette);
+    match &self.palette_shader {
+      Some(shader) => {
+        shader.apply(&self.palettes[index]);
+        self.texture_palette_base
+      },
+      None => self.textures_palette[index],
+    }
+  }
+
+  pub fn reset_palette(&self) {
+    if let Some(shader) = &self.palette_shader {
+      shader.reset();
     }
   }
 
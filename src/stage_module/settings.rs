@@ -0,0 +1,99 @@
+use macroquad::prelude::*;
+
+use crate::{settings::{Settings, SETTINGS_PATH}, input::{Action, Bindings}};
+
+use super::{stage_stack::{Stage, StageAction, StageMessage}, resources::Resources, controls::ControlsStage, button::{Button, clicked_button}};
+
+fn on_off(value: bool) -> &'static str {
+  if value { "on" } else { "off" }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SettingsAction {
+  AutoPause,
+  Fullscreen,
+  ShowDebug,
+  VirtualJoystick,
+  Controls,
+  Back,
+}
+
+pub struct SettingsStage {
+  camera: Camera2D,
+  settings: Settings,
+  bindings: Bindings,
+}
+
+impl SettingsStage {
+  pub fn new() -> Self {
+    Self {
+      camera: Camera2D::from_display_rect(Rect::new(0., 0., screen_width(), screen_height())),
+      settings: Settings::load_or_default(),
+      bindings: Bindings::load_or_default(),
+    }
+  }
+
+  fn save(&self) {
+    if let Err(e) = self.settings.save(SETTINGS_PATH) {
+      warn!("settings could not be saved: {}", e);
+    }
+  }
+
+  fn get_buttons(&self) -> Vec<Button<SettingsAction>> {
+    let items = [
+      (format!("Auto pause: {}", on_off(self.settings.auto_pause)), SettingsAction::AutoPause),
+      (format!("Fullscreen: {}", on_off(self.settings.fullscreen)), SettingsAction::Fullscreen),
+      (format!("Debug info: {}", on_off(self.settings.show_debug)), SettingsAction::ShowDebug),
+      (format!("Touch joystick: {}", on_off(self.settings.virtual_joystick)), SettingsAction::VirtualJoystick),
+      ("Controls".to_owned(), SettingsAction::Controls),
+      ("Back".to_owned(), SettingsAction::Back),
+    ];
+    Button::column(&items, screen_width() / 2., (screen_height() - Button::<SettingsAction>::get_column_height(items.len())) / 2.)
+  }
+}
+
+impl Stage for SettingsStage {
+  fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+    if self.bindings.is_pressed(Action::Back) {
+      return Some(StageAction::Pop);
+    }
+
+    match clicked_button(&self.get_buttons())? {
+      SettingsAction::AutoPause => self.settings.auto_pause = !self.settings.auto_pause,
+      SettingsAction::Fullscreen => {
+        self.settings.fullscreen = !self.settings.fullscreen;
+        set_fullscreen(self.settings.fullscreen);
+      },
+      SettingsAction::ShowDebug => self.settings.show_debug = !self.settings.show_debug,
+      SettingsAction::VirtualJoystick => self.settings.virtual_joystick = !self.settings.virtual_joystick,
+      SettingsAction::Controls => return Some(StageAction::Push(Box::new(ControlsStage::new()))),
+      SettingsAction::Back => return Some(StageAction::Pop),
+    }
+    self.save();
+
+    None
+  }
+
+  fn draw(&self, _resources: &Resources) {
+    set_camera(&self.camera);
+
+    draw_rectangle(0., 0., screen_width(), screen_height(), Color::from_rgba(0, 0, 0, 200));
+    let buttons = self.get_buttons();
+    draw_text("SETTINGS", screen_width() / 2. - 70., buttons[0].rect.y - 16., 40., WHITE);
+    for button in buttons {
+      button.draw();
+    }
+  }
+
+  fn get_name(&self) -> &'static str {
+    "settings"
+  }
+
+  fn on_resume(&mut self, _message: Option<StageMessage>) {
+    self.bindings = Bindings::load_or_default();
+  }
+
+  fn is_transparent(&self) -> bool {
+    true
+  }
+}
@@ -1,16 +1,37 @@
-use super::{resources::{Resources}, playing::PlayingStage, game_over::GameOver};
+use macroquad::prelude::*;
+
+use super::resources::Resources;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StageMessage {
+  UpgradeChosen(usize),
+  Restart,
+  QuitToMenu,
+}
 
-#[derive(Debug, Clone)]
 pub enum StageAction {
   GameQuit,
-  StartGame,
-  GameOver(usize),
-  EndGame,
+  Push(Box<dyn Stage>),
+  Pop,
+  PopWith(StageMessage),
+  Replace(Box<dyn Stage>),
+  PopTo(&'static str),
 }
 
 pub trait Stage {
   fn update(&mut self, resources: &Resources) -> Option<StageAction>;
   fn draw(&self, resources: &Resources);
+  fn get_name(&self) -> &'static str;
+
+  fn is_transparent(&self) -> bool {
+    false
+  }
+
+  fn blocks_update(&self) -> bool {
+    true
+  }
+
+  fn on_resume(&mut self, _message: Option<StageMessage>) {}
 }
 
 pub struct StageStack {
@@ -32,36 +53,165 @@ impl StageStack {
   }
 
   pub fn is_empty(&self) -> bool {
-    return self.stack.is_empty()
+    self.stack.is_empty()
   }
 
-  pub fn update(&mut self, resources: &Resources) {
-    let mut action: Option<StageAction> = None;
+  fn resume_top(&mut self, message: Option<StageMessage>) {
     if let Some(stage) = self.stack.last_mut() {
-      action = stage.update(resources);
+      stage.on_resume(message);
     }
+  }
 
+  pub fn apply(&mut self, action: StageAction) {
     match action {
-      Some(StageAction::GameOver(s)) => {
+      StageAction::GameQuit => {
+        self.stack.clear();
+      },
+      StageAction::Push(stage) => {
+        self.stack.push(stage);
+      },
+      StageAction::Pop => {
         self.stack.pop();
-        self.stack.push(Box::new(GameOver::new(s)));
+        self.resume_top(None);
       },
-      Some(StageAction::GameQuit) => {
-        self.stack.clear();
+      StageAction::PopWith(message) => {
+        self.stack.pop();
+        self.resume_top(Some(message));
       },
-      Some(StageAction::StartGame) => {
-        self.stack.push(Box::new(PlayingStage::new(&resources)));
-      }
-      Some(StageAction::EndGame) => {
+      StageAction::Replace(stage) => {
         self.stack.pop();
+        self.stack.push(stage);
+      },
+      StageAction::PopTo(name) => {
+        match self.stack.iter().rposition(|stage| stage.get_name() == name) {
+          Some(index) => {
+            self.stack.truncate(index + 1);
+            self.resume_top(None);
+          },
+          None => warn!("stage {} is not on the stack", name),
+        }
       },
-      None => ()
     };
   }
 
+  fn get_lowest_updated(&self) -> usize {
+    let mut index = self.stack.len().saturating_sub(1);
+    while index > 0 && !self.stack[index].blocks_update() {
+      index -= 1;
+    }
+    index
+  }
+
+  fn get_lowest_drawn(&self) -> usize {
+    let mut index = self.stack.len().saturating_sub(1);
+    while index > 0 && self.stack[index].is_transparent() {
+      index -= 1;
+    }
+    index
+  }
+
+  pub fn update(&mut self, resources: &Resources) {
+    let lowest = self.get_lowest_updated();
+    for index in (lowest..self.stack.len()).rev() {
+      if let Some(action) = self.stack[index].update(resources) {
+        self.apply(action);
+        return;
+      }
+    }
+  }
+
   pub fn draw(&mut self, resources: &Resources) {
-    if let Some(stage) = self.stack.last_mut() {
+    for stage in self.stack.iter().skip(self.get_lowest_drawn()) {
       stage.draw(resources);
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{rc::Rc, cell::RefCell};
+
+  use super::*;
+
+  struct TestStage {
+    name: &'static str,
+    transparent: bool,
+    blocking: bool,
+    log: Rc<RefCell<Vec<String>>>,
+  }
+
+  impl Stage for TestStage {
+    fn update(&mut self, _resources: &Resources) -> Option<StageAction> {
+      None
+    }
+
+    fn draw(&self, _resources: &Resources) {}
+
+    fn get_name(&self) -> &'static str {
+      self.name
+    }
+
+    fn is_transparent(&self) -> bool {
+      self.transparent
+    }
+
+    fn blocks_update(&self) -> bool {
+      self.blocking
+    }
+
+    fn on_resume(&mut self, message: Option<StageMessage>) {
+      let message = match message {
+        Some(StageMessage::UpgradeChosen(index)) => format!(" {}", index),
+        Some(message) => format!(" {:?}", message),
+        None => String::new(),
+      };
+      self.log.borrow_mut().push(format!("resume {}{}", self.name, message));
+    }
+  }
+
+  fn create(name: &'static str, transparent: bool, blocking: bool, log: &Rc<RefCell<Vec<String>>>) -> Box<dyn Stage> {
+    Box::new(TestStage { name, transparent, blocking, log: log.clone() })
+  }
+
+  #[test]
+  fn actions_change_the_stack() {
+    let log = Rc::new(RefCell::new(vec![]));
+    let mut stack = StageStack::new().with_stages(vec![create("menu", false, true, &log), create("game", false, true, &log)]);
+
+    stack.apply(StageAction::Push(create("dialog", true, true, &log)));
+    stack.apply(StageAction::Push(create("confirm", true, true, &log)));
+    stack.apply(StageAction::PopWith(StageMessage::UpgradeChosen(2)));
+    stack.apply(StageAction::Replace(create("other", true, true, &log)));
+    assert_eq!(stack.stack.iter().map(|s| s.get_name()).collect::<Vec<&str>>(), vec!["menu", "game", "other"]);
+
+    stack.apply(StageAction::PopTo("menu"));
+    stack.apply(StageAction::PopTo("missing"));
+    assert_eq!(stack.stack.len(), 1);
+    assert_eq!(*log.borrow(), vec!["resume dialog 2", "resume menu"]);
+
+    stack.apply(StageAction::GameQuit);
+    assert!(stack.is_empty());
+  }
+
+  #[test]
+  fn transparent_and_non_blocking_stages() {
+    let log = Rc::new(RefCell::new(vec![]));
+    let mut stack = StageStack::new().with_stages(vec![
+      create("menu", false, true, &log),
+      create("game", false, true, &log),
+      create("hud", true, false, &log),
+      create("dialog", true, true, &log),
+    ]);
+
+    assert_eq!(stack.get_lowest_drawn(), 1);
+    assert_eq!(stack.get_lowest_updated(), 3);
+
+    stack.apply(StageAction::Pop);
+    assert_eq!(stack.get_lowest_drawn(), 1);
+    assert_eq!(stack.get_lowest_updated(), 1);
+
+    stack.apply(StageAction::GameQuit);
+    assert_eq!(stack.get_lowest_drawn(), 0);
+    assert_eq!(stack.get_lowest_updated(), 0);
+  }
+}
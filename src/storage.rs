@@ -0,0 +1,73 @@
+// The working directory of an Android app is not writable, files go to its internal storage
+// (`/data/data/<package>/files`), the package name is the process name.
+#[cfg(target_os = "android")]
+fn get_path(path: &str) -> std::path::PathBuf {
+  let cmdline = std::fs::read_to_string("/proc/self/cmdline").unwrap_or_default();
+  let package = cmdline.split('\0').next().unwrap_or_default();
+  std::path::Path::new("/data/data").join(package).join("files").join(path)
+}
+
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+fn get_path(path: &str) -> std::path::PathBuf {
+  std::path::PathBuf::from(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_string(path: &str, data: &str) -> Result<(), String> {
+  let path = get_path(path);
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+  }
+  std::fs::write(path, data).map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_string(path: &str) -> Result<String, String> {
+  std::fs::read_to_string(get_path(path)).map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(path: &str) {
+  let _ = std::fs::remove_file(get_path(path));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn exists(path: &str) -> bool {
+  get_path(path).exists()
+}
+
+// Implemented by the `kgg_storage` plugin registered in index.html, backed by `window.localStorage`.
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+  fn kgg_storage_set(key: *const u8, key_len: usize, value: *const u8, value_len: usize);
+  fn kgg_storage_len(key: *const u8, key_len: usize) -> i32;
+  fn kgg_storage_get(key: *const u8, key_len: usize, buffer: *mut u8, buffer_len: usize);
+  fn kgg_storage_remove(key: *const u8, key_len: usize);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write_string(path: &str, data: &str) -> Result<(), String> {
+  unsafe { kgg_storage_set(path.as_ptr(), path.len(), data.as_ptr(), data.len()) };
+  Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read_string(path: &str) -> Result<String, String> {
+  let len = unsafe { kgg_storage_len(path.as_ptr(), path.len()) };
+  if len < 0 {
+    return Err(format!("{} not found in local storage", path));
+  }
+  let mut buffer = vec![0u8; len as usize];
+  unsafe { kgg_storage_get(path.as_ptr(), path.len(), buffer.as_mut_ptr(), buffer.len()) };
+  String::from_utf8(buffer).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(path: &str) {
+  unsafe { kgg_storage_remove(path.as_ptr(), path.len()) };
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn exists(path: &str) -> bool {
+  unsafe { kgg_storage_len(path.as_ptr(), path.len()) >= 0 }
+}
@@ -1,8 +1,13 @@
 use macroquad::{prelude::*};
 
-use crate::world_module::actor::Actor;
+use crate::{world_module::{actor::Actor, projectile::{Projectile, spawn_projectile_from_actor}}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}, utils::get_vector_rotation};
 
-use super::timer::Timer;
+use super::{animator::AnimState, timer::Timer, rng::Rng, cd::{LAYER_ENEMY_PROJECTILE, MASK_ENEMY_PROJECTILE}};
+
+const ATTACK_RANGE: f32 = 200.;
+const ATTACK_COOLDOWN: f32 = 1.2;
+const ATTACK_DURATION: (f32, f32) = (2., 4.);
+const RELEASE_EVENT: &str = "release";
 
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -10,7 +15,7 @@ pub enum AiState {
   Idle,
   Wandering,
   Following,
-  // Attacking
+  Attacking,
 }
 
 
@@ -40,20 +45,29 @@ impl WeightedStates {
     ])
   }
 
+  pub fn new_idle_wandering_attacking(weights: &[i32; 4]) -> Self {
+    Self::new(vec![
+      (weights[0], AiState::Idle),
+      (weights[1], AiState::Wandering),
+      (weights[2], AiState::Following),
+      (weights[3], AiState::Attacking),
+    ])
+  }
+
   fn get_state_by_weight(&self, weight: i32) -> AiState {
-    let mut result = weight;
+    let mut weight = weight;
     for (w, s) in &self.states {
-      result -= w;
-      if result <= 0 {
+      if weight < *w {
         return *s;
       }
+      weight -= w;
     }
 
     AiState::Idle
   }
 
-  pub fn get_next_state(&self) -> AiState {
-    let weight = rand::gen_range::<i32>(0, self.total);
+  pub fn get_next_state(&self, rng: &mut Rng) -> AiState {
+    let weight = rng.gen_range::<i32>(0, self.total);
     self.get_state_by_weight(weight)
   }
 }
@@ -62,23 +76,26 @@ pub struct Ai {
   state: AiState,
   weighted_states: WeightedStates,
   timer: Timer,
+  attack_timer: Timer,
 }
 
 impl Ai {
-  pub fn new(weighted_states: WeightedStates) -> Self {
-    let timer = Timer::new(rand::gen_range::<f32>(0.5, 2.));
+  pub fn new(weighted_states: WeightedStates, rng: &mut Rng) -> Self {
+    let timer = Timer::new(rng.gen_range::<f32>(0.5, 2.));
     Self {
       state: AiState::Idle,
       timer,
       weighted_states,
+      attack_timer: Timer::new_timeout(ATTACK_COOLDOWN),
     }
   }
 
-  fn refresh_timer(&mut self) {
-    self.timer = Timer::new(rand::gen_range::<f32>(0.5, 2.))
+  fn refresh_timer(&mut self, rng: &mut Rng) {
+    let (min, max) = if self.state == AiState::Attacking { ATTACK_DURATION } else { (0.5, 2.) };
+    self.timer = Timer::new(rng.gen_range::<f32>(min, max))
   }
 
-  pub fn set_state(&mut self, state: AiState, actor: &mut Actor, player_actor: &Actor) {
+  pub fn set_state(&mut self, state: AiState, actor: &mut Actor, player_actor: &Actor, bounds: &Rect, rng: &mut Rng) {
     self.state = state;
       match self.state {
         AiState::Following => {
@@ -86,20 +103,165 @@ impl Ai {
           actor.move_to(tp);
         },
         AiState::Wandering => {
-          let tp = Vec2::new(rand::gen_range::<f32>(32., screen_width() - 32.), rand::gen_range::<f32>(32., screen_height() - 32.));
+          let tp = Vec2::new(rng.gen_range::<f32>(bounds.left() + 32., bounds.right() - 32.), rng.gen_range::<f32>(bounds.top() + 32., bounds.bottom() - 32.));
           actor.move_to(tp);
         },
         AiState::Idle => {
           actor.stop();
-        }
+        },
+        AiState::Attacking => (),
       };
-      self.refresh_timer();
+      self.refresh_timer(rng);
   }
 
-  pub fn update(&mut self, delta_t: f32, actor: &mut Actor, player_actor: &Actor) {
+  fn update_attacking(&mut self, delta_t: f32, actor: &mut Actor, player_actor: &Actor) -> Option<Projectile> {
+    let player_position = player_actor.movable.position;
+    let to_player = player_position - actor.movable.position;
+    if to_player.length() > ATTACK_RANGE {
+      actor.move_to(player_position);
+      return None;
+    }
+    if actor.movable.is_moving() {
+      actor.stop();
+    }
+    if to_player == Vec2::ZERO {
+      return None;
+    }
+    actor.movable.rotation = get_vector_rotation(&to_player);
+
+    self.attack_timer.update(delta_t);
+    let mut attacked = false;
+    if self.attack_timer.is_over() {
+      self.attack_timer.reset();
+      actor.attack();
+      attacked = true;
+    }
+    // without an attack animation the projectile is released as soon as the attack starts
+    let released = if actor.animator.has_animation(AnimState::Attack) { actor.animator.has_event(RELEASE_EVENT) } else { attacked };
+    if !released {
+      return None;
+    }
+    Some(
+      spawn_projectile_from_actor(actor.get_id(), &actor.movable.position, &player_position)
+        .with_collision_layer(LAYER_ENEMY_PROJECTILE, MASK_ENEMY_PROJECTILE)
+    )
+  }
+
+  pub fn update(&mut self, delta_t: f32, actor: &mut Actor, player_actor: &Actor, bounds: &Rect, rng: &mut Rng) -> Option<Projectile> {
     self.timer.update(delta_t);
-    if self.timer.is_just_over() || actor.animation.is_finished() {
-      self.set_state(self.weighted_states.get_next_state(), actor, player_actor);
+    let arrived = self.state != AiState::Attacking && actor.animator.get_state() == AnimState::Idle;
+    if self.timer.is_just_over() || arrived {
+      let next_state = self.weighted_states.get_next_state(rng);
+      self.set_state(next_state, actor, player_actor, bounds, rng);
     };
+
+    if self.state == AiState::Attacking {
+      return self.update_attacking(delta_t, actor, player_actor);
+    }
+    None
+  }
+}
+
+impl Snapshot for AiState {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(*self as u8);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    match reader.read::<u8>()? {
+      0 => Ok(AiState::Idle),
+      1 => Ok(AiState::Wandering),
+      2 => Ok(AiState::Following),
+      3 => Ok(AiState::Attacking),
+      s => Err(format!("invalid ai state {}", s)),
+    }
+  }
+}
+
+impl Snapshot for WeightedStates {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.states.len());
+    for (weight, state) in &self.states {
+      writer.write(weight);
+      writer.write_snapshot(state);
+    }
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let len = reader.read::<usize>()?;
+    let states = (0..len)
+      .map(|_| Ok((reader.read()?, reader.read_snapshot()?)))
+      .collect::<Result<Vec<(i32, AiState)>, String>>()?;
+    Ok(Self::new(states))
+  }
+}
+
+impl Snapshot for Ai {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_snapshot(&self.state);
+    writer.write_snapshot(&self.weighted_states);
+    writer.write_snapshot(&self.timer);
+    writer.write_snapshot(&self.attack_timer);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      state: reader.read_snapshot()?,
+      weighted_states: reader.read_snapshot()?,
+      timer: reader.read_snapshot()?,
+      attack_timer: reader.read_snapshot()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{world_module::world::WORLD_WIDTH, systems::cd::LAYER_ENEMY_PROJECTILE};
+
+  #[test]
+  fn states_picked_by_weight() {
+    let states = WeightedStates::new_idle_wandering_attacking(&[1, 5, 7, 2]);
+
+    assert_eq!(states.get_state_by_weight(0), AiState::Idle);
+    assert_eq!(states.get_state_by_weight(1), AiState::Wandering);
+    assert_eq!(states.get_state_by_weight(5), AiState::Wandering);
+    assert_eq!(states.get_state_by_weight(6), AiState::Following);
+    assert_eq!(states.get_state_by_weight(12), AiState::Following);
+    assert_eq!(states.get_state_by_weight(13), AiState::Attacking);
+    assert_eq!(states.get_state_by_weight(14), AiState::Attacking);
+
+    let attacking = WeightedStates::new_idle_wandering_attacking(&[0, 0, 0, 1]);
+    let mut rng = Rng::new(1);
+    assert!((0..20).all(|_| attacking.get_next_state(&mut rng) == AiState::Attacking));
+  }
+
+  #[test]
+  fn attacking_stops_in_range_and_fires() {
+    let mut rng = Rng::new(1);
+    let bounds = Rect::new(-WORLD_WIDTH / 2., -WORLD_WIDTH / 2., WORLD_WIDTH, WORLD_WIDTH);
+    let player = Actor::new(Vec2::ZERO, 100., 5);
+    let mut enemy = Actor::new(Vec2::new(ATTACK_RANGE + 50., 0.), 100., 2);
+    let mut ai = Ai::new(WeightedStates::new_idle_wandering_attacking(&[0, 0, 0, 1]), &mut rng);
+    ai.set_state(AiState::Attacking, &mut enemy, &player, &bounds, &mut rng);
+
+    assert!(ai.update(0.1, &mut enemy, &player, &bounds, &mut rng).is_none());
+    assert!(enemy.movable.is_moving());
+
+    for _ in 0..10 {
+      enemy.update(0.1);
+      ai.update(0.01, &mut enemy, &player, &bounds, &mut rng);
+    }
+    assert!(!enemy.movable.is_moving());
+
+    let mut fired = None;
+    for _ in 0..200 {
+      fired = fired.or(ai.update(0.01, &mut enemy, &player, &bounds, &mut rng));
+      enemy.update(0.01);
+    }
+    let projectile = fired.expect("attacking ai should fire");
+    assert_eq!(projectile.origin_id, enemy.get_id());
+    assert_eq!(projectile.cd_bounds.get_layer(), LAYER_ENEMY_PROJECTILE);
+    assert!(projectile.movable.velocity.x < 0.);
   }
 }
@@ -1,32 +1,69 @@
 use macroquad::prelude::*;
 
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
 use super::timer::Timer;
 
 
 
 const ANIMATION_THRESHOLD: f32 = 0.07;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayMode {
+  Forward,
+  Reverse,
+  PingPong,
+}
+
 #[derive(Debug, Clone)]
 struct Frames {
   list: Vec<Rect>,
+  durations: Vec<f32>,
   act: usize,
   should_loop: bool,
+  mode: PlayMode,
+  backwards: bool,
 }
 
 impl Frames {
+  pub fn new(list: Vec<Rect>, durations: Vec<f32>, should_loop: bool, mode: PlayMode) -> Self {
+    let act = if mode == PlayMode::Reverse { list.len() - 1 } else { 0 };
+    Self { list, durations, act, should_loop, mode, backwards: false }
+  }
+
+  fn is_at_end(&self) -> bool {
+    match self.mode {
+      PlayMode::Forward => self.act == self.list.len() - 1,
+      PlayMode::Reverse => self.act == 0,
+      PlayMode::PingPong => self.act == 0 && (self.backwards || self.list.len() == 1),
+    }
+  }
+
   pub fn next_frame(&mut self) {
-    self.act = if self.should_loop {
-      (self.act + 1) % self.list.len()
-    } else if self.act < self.list.len() - 1 {
-      self.act + 1
-    } else {
-      self.act
+    let last = self.list.len() - 1;
+    if !self.should_loop && self.is_at_end() {
+      return;
+    }
+    self.act = match self.mode {
+      PlayMode::Forward => if self.act < last { self.act + 1 } else { 0 },
+      PlayMode::Reverse => if self.act > 0 { self.act - 1 } else { last },
+      PlayMode::PingPong if last == 0 => 0,
+      PlayMode::PingPong => {
+        if (self.backwards && self.act == 0) || (!self.backwards && self.act == last) {
+          self.backwards = !self.backwards;
+        }
+        if self.backwards { self.act - 1 } else { self.act + 1 }
+      },
     }
   }
 
   pub fn act_frame(&self) -> Rect {
     self.list[self.act]
   }
+
+  pub fn act_duration(&self) -> f32 {
+    self.durations[self.act]
+  }
 }
 
 
@@ -35,33 +72,164 @@ impl Frames {
 pub struct Animation {
   frames: Frames,
   time: Timer,
+  speed: f32,
+  events: Vec<(usize, String)>,
+  started: bool,
+  just_entered: bool,
+  completed: bool,
 }
 
 impl Animation {
   pub fn new(frames_list: Vec<Rect>, should_loop: bool) -> Self {
+    let durations = vec![ANIMATION_THRESHOLD; frames_list.len()];
+    Self::with_durations(frames_list, durations, should_loop, PlayMode::Forward)
+  }
+
+  pub fn with_durations(frames_list: Vec<Rect>, durations: Vec<f32>, should_loop: bool, mode: PlayMode) -> Self {
+    let frames = Frames::new(frames_list, durations, should_loop, mode);
     Self {
-      frames: Frames {
-        list: frames_list,
-        act: 0,
-        should_loop,
-      },
-      time: Timer::new(ANIMATION_THRESHOLD)
+      time: Timer::new(frames.act_duration()),
+      frames,
+      speed: 1.,
+      events: vec![],
+      started: false,
+      just_entered: false,
+      completed: false,
     }
   }
 
+  // event names are stored in snapshots, so they must not contain whitespace
+  pub fn with_event(mut self, frame: usize, name: &str) -> Self {
+    self.events.push((frame, name.to_owned()));
+    self
+  }
+
+  pub fn set_speed(&mut self, speed: f32) {
+    self.speed = speed.max(0.);
+  }
+
   pub fn is_finished(&self) -> bool {
-    !self.frames.should_loop && self.frames.act == self.frames.list.len() - 1
+    !self.frames.should_loop && self.frames.is_at_end()
+  }
+
+  // unlike is_finished, waits until the last frame was shown for its whole duration
+  pub fn is_completed(&self) -> bool {
+    self.completed
   }
 
   pub fn get_act_frame(&self) -> Rect {
     self.frames.act_frame()
   }
 
+  // events of the frame entered during the last update
+  pub fn get_events(&self) -> impl Iterator<Item = &str> {
+    let act = self.frames.act;
+    self.events
+      .iter()
+      .filter(move |(frame, _)| self.just_entered && *frame == act)
+      .map(|(_, name)| name.as_str())
+  }
+
+  pub fn has_event(&self, name: &str) -> bool {
+    self.get_events().any(|event| event == name)
+  }
+
   pub fn update(&mut self, delta_t: f32) {
-    self.time.update(delta_t);
+    self.just_entered = !self.started;
+    self.started = true;
+    self.time.update(delta_t * self.speed);
     if self.time.is_just_over() {
+      self.completed = self.is_finished();
+      let previous = self.frames.act;
       self.frames.next_frame();
+      self.time.set_treshold(self.frames.act_duration());
+      self.just_entered |= previous != self.frames.act;
+    }
+  }
+}
+
+impl Snapshot for PlayMode {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(*self as u8);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    match reader.read::<u8>()? {
+      0 => Ok(PlayMode::Forward),
+      1 => Ok(PlayMode::Reverse),
+      2 => Ok(PlayMode::PingPong),
+      m => Err(format!("invalid play mode {}", m)),
+    }
+  }
+}
+
+impl Snapshot for Frames {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.list.len());
+    for (frame, duration) in self.list.iter().zip(&self.durations) {
+      writer.write_rect(frame);
+      writer.write(duration);
+    }
+    writer.write(self.act);
+    writer.write_bool(self.should_loop);
+    writer.write_snapshot(&self.mode);
+    writer.write_bool(self.backwards);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let len = reader.read::<usize>()?;
+    let (list, durations) = (0..len)
+      .map(|_| Ok((reader.read_rect()?, reader.read::<f32>()?)))
+      .collect::<Result<Vec<(Rect, f32)>, String>>()?
+      .into_iter()
+      .unzip();
+    let act = reader.read::<usize>()?;
+    if act >= len {
+      return Err(format!("animation frame {} out of range", act));
     }
+    Ok(Self {
+      list,
+      durations,
+      act,
+      should_loop: reader.read_bool()?,
+      mode: reader.read_snapshot()?,
+      backwards: reader.read_bool()?,
+    })
+  }
+}
+
+impl Snapshot for Animation {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_snapshot(&self.frames);
+    writer.write_snapshot(&self.time);
+    writer.write(self.speed);
+    writer.write(self.events.len());
+    for (frame, name) in &self.events {
+      writer.write(frame);
+      writer.write(name);
+    }
+    writer.write_bool(self.started);
+    writer.write_bool(self.just_entered);
+    writer.write_bool(self.completed);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let frames = reader.read_snapshot()?;
+    let time = reader.read_snapshot()?;
+    let speed = reader.read()?;
+    let len = reader.read::<usize>()?;
+    let events = (0..len)
+      .map(|_| Ok((reader.read()?, reader.read()?)))
+      .collect::<Result<Vec<(usize, String)>, String>>()?;
+    Ok(Self {
+      frames,
+      time,
+      speed,
+      events,
+      started: reader.read_bool()?,
+      just_entered: reader.read_bool()?,
+      completed: reader.read_bool()?,
+    })
   }
 }
 
@@ -70,15 +238,19 @@ mod tests {
   mod frames {
     use super::super::*;
 
-    fn create(should_loop: bool) -> Frames {
+    fn create_with_mode(should_loop: bool, mode: PlayMode) -> Frames {
       let r1 = Rect::new(0., 0., 16., 16.);
       let r2 = Rect::new(16., 0., 16., 16.);
       let r3 = Rect::new(32., 0., 16., 16.);
-      Frames {
-        list: vec![r1, r2, r3],
-        act: 0,
-        should_loop
-      }
+      Frames::new(vec![r1, r2, r3], vec![ANIMATION_THRESHOLD; 3], should_loop, mode)
+    }
+
+    fn create(should_loop: bool) -> Frames {
+      create_with_mode(should_loop, PlayMode::Forward)
+    }
+
+    fn play(frames: &mut Frames, count: usize) -> Vec<usize> {
+      (0..count).map(|_| { frames.next_frame(); frames.act }).collect()
     }
 
     #[test]
@@ -129,6 +301,26 @@ mod tests {
       frames.next_frame();
       assert_eq!(frames.act_frame(), frames.list[2]);
     }
+
+    #[test]
+    fn reverse() {
+      let mut frames = create_with_mode(true, PlayMode::Reverse);
+      assert_eq!(frames.act, 2);
+      assert_eq!(play(&mut frames, 4), vec![1, 0, 2, 1]);
+
+      let mut frames = create_with_mode(false, PlayMode::Reverse);
+      assert_eq!(play(&mut frames, 3), vec![1, 0, 0]);
+      assert!(frames.is_at_end());
+    }
+
+    #[test]
+    fn ping_pong() {
+      let mut frames = create_with_mode(true, PlayMode::PingPong);
+      assert_eq!(play(&mut frames, 6), vec![1, 2, 1, 0, 1, 2]);
+
+      let mut frames = create_with_mode(false, PlayMode::PingPong);
+      assert_eq!(play(&mut frames, 6), vec![1, 2, 1, 0, 0, 0]);
+    }
   }
 
   #[cfg(test)]
@@ -190,5 +382,39 @@ mod tests {
       anim.update(ANIMATION_THRESHOLD);
       assert_eq!(anim.get_act_frame(), r2);
     }
+
+    #[test]
+    fn durations_and_speed() {
+      let r1 = Rect::new(0., 0., 16., 16.);
+      let r2 = Rect::new(16., 0., 16., 16.);
+      let mut anim = Animation::with_durations(vec![r1, r2], vec![0.1, 0.5], true, PlayMode::Forward);
+
+      anim.update(0.11);
+      assert_eq!(anim.get_act_frame(), r2);
+      anim.update(0.3);
+      assert_eq!(anim.get_act_frame(), r2);
+
+      anim.set_speed(2.);
+      anim.update(0.15);
+      assert_eq!(anim.get_act_frame(), r1);
+    }
+
+    #[test]
+    fn events() {
+      let r1 = Rect::new(0., 0., 16., 16.);
+      let r2 = Rect::new(16., 0., 16., 16.);
+      let mut anim = Animation::new(vec![r1, r2], false).with_event(0, "start").with_event(1, "release");
+
+      anim.update(0.01);
+      assert_eq!(anim.get_events().collect::<Vec<&str>>(), vec!["start"]);
+      anim.update(0.01);
+      assert!(!anim.has_event("start"));
+      anim.update(ANIMATION_THRESHOLD);
+      assert!(anim.has_event("release"));
+      assert!(anim.is_finished() && !anim.is_completed());
+      anim.update(ANIMATION_THRESHOLD + 0.01);
+      assert!(!anim.has_event("release"));
+      assert!(anim.is_completed());
+    }
   }
 }
@@ -0,0 +1,303 @@
+use macroquad::prelude::*;
+
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
+use super::{animation::Animation, atlas::get_atlas};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimState {
+  Idle,
+  Walk,
+  Hurt,
+  Attack,
+  Death,
+}
+
+pub const ANIM_STATES: [AnimState; 5] = [AnimState::Idle, AnimState::Walk, AnimState::Hurt, AnimState::Attack, AnimState::Death];
+
+impl AnimState {
+  // also the suffix of the atlas tag, e.g. "enemy_walk"
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      AnimState::Idle => "idle",
+      AnimState::Walk => "walk",
+      AnimState::Hurt => "hurt",
+      AnimState::Attack => "attack",
+      AnimState::Death => "death",
+    }
+  }
+
+  fn is_looping(&self) -> bool {
+    matches!(self, AnimState::Idle | AnimState::Walk)
+  }
+
+  // sprites without these tags skip hurt and attack and hold their last frame on death
+  pub fn is_optional(&self) -> bool {
+    matches!(self, AnimState::Hurt | AnimState::Attack | AnimState::Death)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Condition {
+  Moving,
+  Stopped,
+  Damaged,
+  AttackTriggered,
+  Dead,
+  Finished,
+}
+
+struct Transition {
+  from: &'static [AnimState],
+  // None returns to the state that was active before the one-shot animation
+  to: Option<AnimState>,
+  condition: Condition,
+}
+
+// checked in order, the first matching transition wins
+const TRANSITIONS: [Transition; 6] = [
+  Transition { from: &[AnimState::Idle, AnimState::Walk, AnimState::Hurt, AnimState::Attack], to: Some(AnimState::Death), condition: Condition::Dead },
+  Transition { from: &[AnimState::Idle, AnimState::Walk, AnimState::Attack], to: Some(AnimState::Hurt), condition: Condition::Damaged },
+  Transition { from: &[AnimState::Idle, AnimState::Walk], to: Some(AnimState::Attack), condition: Condition::AttackTriggered },
+  Transition { from: &[AnimState::Idle], to: Some(AnimState::Walk), condition: Condition::Moving },
+  Transition { from: &[AnimState::Walk], to: Some(AnimState::Idle), condition: Condition::Stopped },
+  Transition { from: &[AnimState::Hurt, AnimState::Attack], to: None, condition: Condition::Finished },
+];
+
+#[derive(Debug, Clone)]
+pub struct Animator {
+  sprite: String,
+  state: AnimState,
+  previous: AnimState,
+  animation: Animation,
+  moving: bool,
+  walk_speed: f32,
+  health: isize,
+  damaged: bool,
+  attack_triggered: bool,
+}
+
+impl Animator {
+  pub fn new(sprite: &str, health: isize) -> Self {
+    Self {
+      sprite: sprite.to_owned(),
+      state: AnimState::Idle,
+      previous: AnimState::Idle,
+      animation: get_atlas().get_animation(&format!("{}_{}", sprite, AnimState::Idle.get_name()), true),
+      moving: false,
+      walk_speed: 1.,
+      health,
+      damaged: false,
+      attack_triggered: false,
+    }
+  }
+
+  pub fn get_state(&self) -> AnimState {
+    self.state
+  }
+
+  pub fn get_act_frame(&self) -> Rect {
+    self.animation.get_act_frame()
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.animation.is_completed()
+  }
+
+  pub fn has_event(&self, name: &str) -> bool {
+    self.animation.has_event(name)
+  }
+
+  pub fn has_animation(&self, state: AnimState) -> bool {
+    !state.is_optional() || get_atlas().has_tag(&self.get_tag(state))
+  }
+
+  fn get_tag(&self, state: AnimState) -> String {
+    format!("{}_{}", self.sprite, state.get_name())
+  }
+
+  // death is entered even without its animation, otherwise the actor would never finish dying
+  fn can_enter(&self, state: Option<AnimState>) -> bool {
+    match state {
+      Some(AnimState::Death) | None => true,
+      Some(state) => self.has_animation(state),
+    }
+  }
+
+  pub fn set_moving(&mut self, moving: bool) {
+    self.moving = moving;
+    self.apply_transitions();
+  }
+
+  pub fn set_walk_speed(&mut self, speed: f32) {
+    self.walk_speed = speed;
+  }
+
+  pub fn set_health(&mut self, health: isize) {
+    self.damaged |= health < self.health;
+    self.health = health;
+    self.apply_transitions();
+  }
+
+  pub fn trigger_attack(&mut self) {
+    self.attack_triggered = true;
+    self.apply_transitions();
+  }
+
+  fn is_met(&self, condition: Condition) -> bool {
+    match condition {
+      Condition::Moving => self.moving,
+      Condition::Stopped => !self.moving,
+      Condition::Damaged => self.damaged,
+      Condition::AttackTriggered => self.attack_triggered,
+      Condition::Dead => self.health <= 0,
+      Condition::Finished => self.animation.is_completed(),
+    }
+  }
+
+  fn enter(&mut self, state: AnimState) {
+    if self.state.is_looping() {
+      self.previous = self.state;
+    }
+    self.state = state;
+    self.animation = if self.has_animation(state) {
+      get_atlas().get_animation(&self.get_tag(state), state.is_looping())
+    } else {
+      Animation::new(vec![self.animation.get_act_frame()], false)
+    };
+  }
+
+  fn apply_transitions(&mut self) {
+    // returning from a one-shot can enable another transition, e.g. walk -> idle
+    for _ in 0..TRANSITIONS.len() {
+      let transition = TRANSITIONS.iter().find(|t| t.from.contains(&self.state) && self.is_met(t.condition) && self.can_enter(t.to));
+      match transition {
+        Some(transition) => {
+          let next = transition.to.unwrap_or(self.previous);
+          self.enter(next);
+        },
+        None => break,
+      }
+    }
+    self.damaged = false;
+    self.attack_triggered = false;
+  }
+
+  pub fn update(&mut self, delta_t: f32) {
+    if self.state == AnimState::Walk {
+      self.animation.set_speed(self.walk_speed);
+    }
+    self.animation.update(delta_t);
+    self.apply_transitions();
+  }
+}
+
+impl Snapshot for AnimState {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(*self as u8);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let tag = reader.read::<u8>()?;
+    ANIM_STATES.get(tag as usize).copied().ok_or_else(|| format!("invalid animation state {}", tag))
+  }
+}
+
+impl Snapshot for Animator {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(&self.sprite);
+    writer.write_snapshot(&self.state);
+    writer.write_snapshot(&self.previous);
+    writer.write_snapshot(&self.animation);
+    writer.write_bool(self.moving);
+    writer.write(self.walk_speed);
+    writer.write(self.health);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      sprite: reader.read()?,
+      state: reader.read_snapshot()?,
+      previous: reader.read_snapshot()?,
+      animation: reader.read_snapshot()?,
+      moving: reader.read_bool()?,
+      walk_speed: reader.read()?,
+      health: reader.read()?,
+      damaged: false,
+      attack_triggered: false,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(animator: &mut Animator, seconds: f32) {
+    for _ in 0..(seconds / 0.01) as usize {
+      animator.update(0.01);
+    }
+  }
+
+  #[test]
+  fn walk_and_idle() {
+    let mut animator = Animator::new("actor", 3);
+
+    animator.set_moving(true);
+    assert_eq!(animator.get_state(), AnimState::Walk);
+    run(&mut animator, 1.);
+    assert_eq!(animator.get_state(), AnimState::Walk);
+    animator.set_moving(false);
+    assert_eq!(animator.get_state(), AnimState::Idle);
+  }
+
+  #[test]
+  fn one_shots_return_to_previous_state() {
+    let mut animator = Animator::new("enemy", 3);
+    animator.set_moving(true);
+
+    animator.set_health(2);
+    assert_eq!(animator.get_state(), AnimState::Hurt);
+    animator.set_health(3);
+    assert_eq!(animator.get_state(), AnimState::Hurt);
+    run(&mut animator, 1.);
+    assert_eq!(animator.get_state(), AnimState::Walk);
+
+    animator.set_moving(false);
+    animator.trigger_attack();
+    assert_eq!(animator.get_state(), AnimState::Attack);
+    run(&mut animator, 1.);
+    assert_eq!(animator.get_state(), AnimState::Idle);
+  }
+
+  #[test]
+  fn death_is_final() {
+    let mut animator = Animator::new("actor", 1);
+    animator.trigger_attack();
+
+    animator.set_health(0);
+    assert_eq!(animator.get_state(), AnimState::Death);
+    animator.set_moving(true);
+    animator.set_health(-1);
+    run(&mut animator, 2.);
+    assert_eq!(animator.get_state(), AnimState::Death);
+    assert!(animator.is_finished());
+  }
+
+  #[test]
+  fn missing_optional_animations_are_skipped() {
+    let mut animator = Animator::new("enemy", 3);
+    animator.sprite = "missing".to_owned();
+
+    animator.set_health(2);
+    animator.trigger_attack();
+    assert_eq!(animator.get_state(), AnimState::Idle);
+    animator.set_moving(true);
+    assert_eq!(animator.get_state(), AnimState::Walk);
+
+    animator.set_health(0);
+    assert_eq!(animator.get_state(), AnimState::Death);
+    run(&mut animator, 1.);
+    assert!(animator.is_finished());
+  }
+}
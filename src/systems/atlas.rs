@@ -0,0 +1,211 @@
+use std::sync::OnceLock;
+
+use macroquad::prelude::*;
+
+use crate::json::{parse_json, JsonValue};
+
+use super::animation::{Animation, PlayMode};
+
+pub const DEFAULT_ATLAS: &str = include_str!("../../assets/frames.json");
+
+static ATLAS: OnceLock<Atlas> = OnceLock::new();
+
+// returns the play mode and whether the tag frames should be reversed first
+fn parse_direction(value: &str) -> Result<(PlayMode, bool), String> {
+  match value {
+    "forward" => Ok((PlayMode::Forward, false)),
+    "reverse" => Ok((PlayMode::Reverse, false)),
+    "pingpong" => Ok((PlayMode::PingPong, false)),
+    "pingpong_reverse" => Ok((PlayMode::PingPong, true)),
+    _ => Err(format!("unknown tag direction: {}", value)),
+  }
+}
+
+// tag user data lists frame events as "name@frame", frames counted from the tag start
+fn parse_events(name: &str, data: &str, frames_count: usize) -> Result<Vec<(usize, String)>, String> {
+  data.split_whitespace()
+    .map(|event| match event.split_once('@') {
+      Some((event_name, frame)) if !event_name.is_empty() => match frame.parse::<usize>() {
+        Ok(frame) if frame < frames_count => Ok((frame, event_name.to_owned())),
+        _ => Err(format!("tag {} has invalid event frame: {}", name, event)),
+      },
+      _ => Err(format!("tag {} has invalid event: {}", name, event)),
+    })
+    .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasFrame {
+  pub rect: Rect,
+  pub duration: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AtlasTag {
+  pub name: String,
+  pub from: usize,
+  pub to: usize,
+  pub mode: PlayMode,
+  pub reversed: bool,
+  pub events: Vec<(usize, String)>,
+}
+
+// Sprite sheet metadata as exported by Aseprite (File > Export Sprite Sheet, JSON data),
+// both the "array" and the "hash" frame layouts are supported.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+  frames: Vec<AtlasFrame>,
+  tags: Vec<AtlasTag>,
+}
+
+fn get_number(value: &JsonValue, key: &str) -> Result<f32, String> {
+  value.get(key).and_then(|v| v.as_f32()).ok_or_else(|| format!("missing number: {}", key))
+}
+
+fn parse_frame(value: &JsonValue) -> Result<AtlasFrame, String> {
+  let rect = value.get("frame").ok_or_else(|| "frame without rect".to_owned())?;
+  Ok(AtlasFrame {
+    rect: Rect::new(get_number(rect, "x")?, get_number(rect, "y")?, get_number(rect, "w")?, get_number(rect, "h")?),
+    duration: get_number(value, "duration")? / 1000.,
+  })
+}
+
+fn parse_tag(value: &JsonValue, frames_count: usize) -> Result<AtlasTag, String> {
+  let name = value.get("name").and_then(|v| v.as_str()).ok_or_else(|| "tag without name".to_owned())?;
+  let from = get_number(value, "from")? as usize;
+  let to = get_number(value, "to")? as usize;
+  if from > to || to >= frames_count {
+    return Err(format!("tag {} has invalid frame range {}..{}", name, from, to));
+  }
+  let (mode, reversed) = parse_direction(value.get("direction").and_then(|v| v.as_str()).unwrap_or("forward"))?;
+  Ok(AtlasTag {
+    name: name.to_owned(),
+    from,
+    to,
+    mode,
+    reversed,
+    events: parse_events(name, value.get("data").and_then(|v| v.as_str()).unwrap_or(""), to - from + 1)?,
+  })
+}
+
+impl Atlas {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let root = parse_json(data)?;
+    let frames = match root.get("frames") {
+      Some(JsonValue::Array(values)) => values.iter().map(parse_frame).collect::<Result<Vec<AtlasFrame>, String>>()?,
+      Some(JsonValue::Object(entries)) => entries.iter().map(|(_, v)| parse_frame(v)).collect::<Result<Vec<AtlasFrame>, String>>()?,
+      _ => return Err("atlas has no frames".to_owned()),
+    };
+    if frames.is_empty() {
+      return Err("atlas has no frames".to_owned());
+    }
+
+    let tags = root.get("meta")
+      .and_then(|meta| meta.get("frameTags"))
+      .and_then(|tags| tags.as_array())
+      .map(|tags| tags.iter().map(|tag| parse_tag(tag, frames.len())).collect::<Result<Vec<AtlasTag>, String>>())
+      .unwrap_or_else(|| Ok(vec![]))?;
+
+    Ok(Self { frames, tags })
+  }
+
+  fn get_tag(&self, name: &str) -> Option<&AtlasTag> {
+    self.tags.iter().find(|tag| tag.name == name)
+  }
+
+  pub fn has_tag(&self, name: &str) -> bool {
+    self.get_tag(name).is_some()
+  }
+
+  pub fn get_frames(&self, name: &str) -> Option<Vec<AtlasFrame>> {
+    let tag = self.get_tag(name)?;
+    let mut frames = self.frames[tag.from..=tag.to].to_vec();
+    if tag.reversed {
+      frames.reverse();
+    }
+    Some(frames)
+  }
+
+  pub fn get_animation(&self, name: &str, should_loop: bool) -> Animation {
+    let tag = match self.get_tag(name) {
+      Some(tag) => tag,
+      None => {
+        warn!("sprite atlas has no tag {}", name);
+        return Animation::new(vec![self.frames[0].rect], should_loop);
+      },
+    };
+    let frames = self.get_frames(name).unwrap_or_default();
+    let animation = Animation::with_durations(
+      frames.iter().map(|f| f.rect).collect(),
+      frames.iter().map(|f| f.duration).collect(),
+      should_loop,
+      tag.mode
+    );
+    tag.events.iter().fold(animation, |animation, (frame, event)| animation.with_event(*frame, event))
+  }
+}
+
+impl Default for Atlas {
+  fn default() -> Self {
+    Self::parse(DEFAULT_ATLAS).expect("assets/frames.json should be valid")
+  }
+}
+
+pub fn set_atlas(atlas: Atlas) {
+  if ATLAS.set(atlas).is_err() {
+    warn!("sprite atlas is already set");
+  }
+}
+
+pub fn get_atlas() -> &'static Atlas {
+  ATLAS.get_or_init(Atlas::default)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HASH_ATLAS: &str = r#"{
+    "frames": {
+      "a 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "duration": 100 },
+      "a 1.aseprite": { "frame": { "x": 8, "y": 0, "w": 8, "h": 8 }, "duration": 300 },
+      "a 2.aseprite": { "frame": { "x": 16, "y": 0, "w": 8, "h": 8 }, "duration": 100 }
+    },
+    "meta": { "frameTags": [
+      { "name": "back", "from": 0, "to": 2, "direction": "reverse" },
+      { "name": "bounce", "from": 0, "to": 2, "direction": "pingpong", "data": "hit@1" }
+    ] }
+  }"#;
+
+  #[test]
+  fn default_atlas_has_sprites() {
+    let atlas = Atlas::default();
+
+    for tag in ["actor_idle", "actor_walk", "enemy_idle", "enemy_walk", "fireball", "fire_particle"] {
+      assert!(atlas.has_tag(tag), "missing tag {}", tag);
+    }
+    assert_eq!(atlas.get_frames("actor_walk").map(|f| f.len()), Some(4));
+    assert_eq!(atlas.get_animation("actor_idle", false).get_act_frame(), Rect::new(0., 0., 16., 16.));
+  }
+
+  #[test]
+  fn tag_animations() {
+    let atlas = Atlas::parse(HASH_ATLAS).expect("atlas should be parsed");
+
+    assert_eq!(atlas.get_frames("back").map(|f| f[1].duration), Some(0.3));
+    assert_eq!(atlas.get_animation("back", false).get_act_frame().x, 16.);
+    assert!(atlas.get_frames("missing").is_none());
+
+    let mut bounce = atlas.get_animation("bounce", true);
+    bounce.update(0.11);
+    assert_eq!(bounce.get_act_frame().x, 8.);
+    assert!(bounce.has_event("hit"));
+    bounce.update(0.2);
+    assert_eq!(bounce.get_act_frame().x, 8.);
+    assert!(!bounce.has_event("hit"));
+
+    assert!(Atlas::parse(r#"{ "frames": [], "meta": {} }"#).is_err());
+    assert!(Atlas::parse(&HASH_ATLAS.replace("\"to\": 2", "\"to\": 5")).is_err());
+    assert!(Atlas::parse(&HASH_ATLAS.replace("hit@1", "hit@3")).is_err());
+  }
+}
@@ -1,33 +1,460 @@
+use std::collections::HashMap;
+
 use macroquad::{prelude::*};
 
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
+
+const CAPSULE_SEARCH_ITERATIONS: usize = 24;
+
+pub const LAYER_PLAYER: u32 = 1 << 0;
+pub const LAYER_ENEMY: u32 = 1 << 1;
+pub const LAYER_PLAYER_PROJECTILE: u32 = 1 << 2;
+pub const LAYER_ENEMY_PROJECTILE: u32 = 1 << 3;
+pub const LAYER_PICKUP: u32 = 1 << 4;
+pub const LAYER_WALL: u32 = 1 << 5;
+
+pub const MASK_PLAYER: u32 = LAYER_ENEMY | LAYER_ENEMY_PROJECTILE | LAYER_PICKUP | LAYER_WALL;
+pub const MASK_ENEMY: u32 = LAYER_PLAYER | LAYER_ENEMY | LAYER_PLAYER_PROJECTILE | LAYER_WALL;
+pub const MASK_PLAYER_PROJECTILE: u32 = LAYER_ENEMY | LAYER_WALL;
+pub const MASK_ENEMY_PROJECTILE: u32 = LAYER_PLAYER | LAYER_WALL;
+pub const MASK_PICKUP: u32 = LAYER_PLAYER;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CdShape {
+  Aabb { w: f32, h: f32 },
+  Circle { radius: f32 },
+  Capsule { half_length: f32, radius: f32, rotation: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+  pub normal: Vec2,
+  pub depth: f32,
+}
+
+impl Contact {
+  fn flipped(self) -> Self {
+    Self { normal: -self.normal, depth: self.depth }
+  }
+}
+
+fn closest_point_on_segment(a: Vec2, b: Vec2, point: Vec2) -> Vec2 {
+  let ab = b - a;
+  let len_sq = ab.length_squared();
+  if len_sq == 0. {
+    return a;
+  }
+  a + ab * ((point - a).dot(ab) / len_sq).clamp(0., 1.)
+}
+
+fn closest_points_between_segments(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> (Vec2, Vec2) {
+  let d1 = q1 - p1;
+  let d2 = q2 - p2;
+  let r = p1 - p2;
+  let a = d1.length_squared();
+  let e = d2.length_squared();
+  let f = d2.dot(r);
+
+  if a == 0. && e == 0. {
+    return (p1, p2);
+  }
+  if a == 0. {
+    return (p1, p2 + d2 * (f / e).clamp(0., 1.));
+  }
+  let c = d1.dot(r);
+  if e == 0. {
+    return (p1 + d1 * (-c / a).clamp(0., 1.), p2);
+  }
+
+  let b = d1.dot(d2);
+  let denom = a * e - b * b;
+  let mut s = if denom != 0. { ((b * f - c * e) / denom).clamp(0., 1.) } else { 0. };
+  let mut t = (b * s + f) / e;
+  if t < 0. {
+    t = 0.;
+    s = (-c / a).clamp(0., 1.);
+  } else if t > 1. {
+    t = 1.;
+    s = ((b - c) / a).clamp(0., 1.);
+  }
+
+  (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn distance_to_aabb_squared(point: Vec2, center: Vec2, half: Vec2) -> f32 {
+  let closest = point.clamp(center - half, center + half);
+  point.distance_squared(closest)
+}
+
+fn circle_vs_circle(pos_a: Vec2, radius_a: f32, pos_b: Vec2, radius_b: f32) -> Option<Contact> {
+  let delta = pos_a - pos_b;
+  let distance = delta.length();
+  let depth = radius_a + radius_b - distance;
+  if depth <= 0. {
+    return None;
+  }
+  let normal = if distance > 0. { delta / distance } else { Vec2::X };
+  Some(Contact { normal, depth })
+}
+
+fn circle_vs_aabb(pos: Vec2, radius: f32, center: Vec2, half: Vec2) -> Option<Contact> {
+  let closest = pos.clamp(center - half, center + half);
+  let delta = pos - closest;
+  let distance_sq = delta.length_squared();
+
+  if distance_sq > 0. {
+    let distance = distance_sq.sqrt();
+    if distance >= radius {
+      return None;
+    }
+    return Some(Contact { normal: delta / distance, depth: radius - distance });
+  }
+
+  let offset = pos - center;
+  let overlap = half - offset.abs();
+  if overlap.x < overlap.y {
+    Some(Contact { normal: Vec2::new(offset.x.signum(), 0.), depth: overlap.x + radius })
+  } else {
+    Some(Contact { normal: Vec2::new(0., offset.y.signum()), depth: overlap.y + radius })
+  }
+}
+
+fn aabb_vs_aabb(pos_a: Vec2, half_a: Vec2, pos_b: Vec2, half_b: Vec2) -> Option<Contact> {
+  let offset = pos_a - pos_b;
+  let overlap = half_a + half_b - offset.abs();
+  if overlap.x <= 0. || overlap.y <= 0. {
+    return None;
+  }
+  if overlap.x < overlap.y {
+    Some(Contact { normal: Vec2::new(if offset.x < 0. { -1. } else { 1. }, 0.), depth: overlap.x })
+  } else {
+    Some(Contact { normal: Vec2::new(0., if offset.y < 0. { -1. } else { 1. }), depth: overlap.y })
+  }
+}
 
 #[derive(Debug, Clone)]
 pub struct CdBounds {
   position: Vec2,
-  w: f32,
-  h: f32,
+  shape: CdShape,
+  layer: u32,
+  mask: u32,
 }
 
 impl CdBounds {
   pub fn new(position: Vec2, w: f32, h: f32) -> Self {
     Self {
       position,
-      w,
-      h,
+      shape: CdShape::Aabb { w, h },
+      layer: 0,
+      mask: 0,
+    }
+  }
+
+  pub fn new_circle(position: Vec2, radius: f32) -> Self {
+    Self {
+      position,
+      shape: CdShape::Circle { radius },
+      layer: 0,
+      mask: 0,
     }
   }
 
+  pub fn new_capsule(position: Vec2, half_length: f32, radius: f32, rotation: f32) -> Self {
+    Self {
+      position,
+      shape: CdShape::Capsule { half_length, radius, rotation },
+      layer: 0,
+      mask: 0,
+    }
+  }
+
+  pub fn with_layer(mut self, layer: u32, mask: u32) -> Self {
+    self.set_layer(layer, mask);
+    self
+  }
+
+  pub fn set_layer(&mut self, layer: u32, mask: u32) {
+    self.layer = layer;
+    self.mask = mask;
+  }
+
+  pub fn get_layer(&self) -> u32 {
+    self.layer
+  }
+
+  pub fn get_mask(&self) -> u32 {
+    self.mask
+  }
+
+  pub fn can_collide_with(&self, other: &CdBounds) -> bool {
+    self.mask & other.layer != 0 && other.mask & self.layer != 0
+  }
+
   pub fn update_position(&mut self, position: &Vec2) {
     self.position.x = position.x;
     self.position.y = position.y;
   }
 
+  pub fn update_rotation(&mut self, new_rotation: f32) {
+    if let CdShape::Capsule { rotation, .. } = &mut self.shape {
+      *rotation = new_rotation;
+    }
+  }
+
+  pub fn get_position(&self) -> Vec2 {
+    self.position
+  }
+
+  pub fn get_shape(&self) -> CdShape {
+    self.shape
+  }
+
+  pub fn get_segment(&self) -> Option<(Vec2, Vec2)> {
+    match self.shape {
+      CdShape::Capsule { half_length, rotation, .. } => {
+        let direction = Vec2::new(rotation.cos(), rotation.sin()) * half_length;
+        Some((self.position - direction, self.position + direction))
+      },
+      _ => None,
+    }
+  }
+
   pub fn get_rect(&self) -> Rect {
-    Rect::new(self.position.x - self.w / 2., self.position.y - self.h / 2., self.w, self.h)
+    match self.shape {
+      CdShape::Aabb { w, h } => Rect::new(self.position.x - w / 2., self.position.y - h / 2., w, h),
+      CdShape::Circle { radius } => Rect::new(self.position.x - radius, self.position.y - radius, radius * 2., radius * 2.),
+      CdShape::Capsule { half_length, radius, rotation } => {
+        let extent = Vec2::new(rotation.cos(), rotation.sin()).abs() * half_length + Vec2::splat(radius);
+        Rect::new(self.position.x - extent.x, self.position.y - extent.y, extent.x * 2., extent.y * 2.)
+      },
+    }
+  }
+
+  fn get_capsule_point_closest_to(&self, other: &CdBounds) -> Vec2 {
+    let (a, b) = match self.get_segment() {
+      Some(segment) => segment,
+      None => return self.position,
+    };
+
+    match other.shape {
+      CdShape::Circle { .. } => closest_point_on_segment(a, b, other.position),
+      CdShape::Aabb { w, h } => {
+        let half = Vec2::new(w / 2., h / 2.);
+        let (mut low, mut high) = (0., 1.);
+        for _ in 0..CAPSULE_SEARCH_ITERATIONS {
+          let t1 = low + (high - low) / 3.;
+          let t2 = high - (high - low) / 3.;
+          if distance_to_aabb_squared(a.lerp(b, t1), other.position, half) <= distance_to_aabb_squared(a.lerp(b, t2), other.position, half) {
+            high = t2;
+          } else {
+            low = t1;
+          }
+        }
+        a.lerp(b, (low + high) / 2.)
+      },
+      CdShape::Capsule { .. } => match other.get_segment() {
+        Some((c, d)) => closest_points_between_segments(a, b, c, d).0,
+        None => self.position,
+      },
+    }
+  }
+
+  pub fn contact(&self, other: &CdBounds) -> Option<Contact> {
+    match (self.shape, other.shape) {
+      (CdShape::Aabb { w: w_a, h: h_a }, CdShape::Aabb { w: w_b, h: h_b }) =>
+        aabb_vs_aabb(self.position, Vec2::new(w_a / 2., h_a / 2.), other.position, Vec2::new(w_b / 2., h_b / 2.)),
+      (CdShape::Circle { radius: r_a }, CdShape::Circle { radius: r_b }) =>
+        circle_vs_circle(self.position, r_a, other.position, r_b),
+      (CdShape::Circle { radius }, CdShape::Aabb { w, h }) =>
+        circle_vs_aabb(self.position, radius, other.position, Vec2::new(w / 2., h / 2.)),
+      (CdShape::Aabb { .. }, CdShape::Circle { .. }) =>
+        other.contact(self).map(Contact::flipped),
+      (CdShape::Capsule { radius: r_a, .. }, CdShape::Capsule { radius: r_b, .. }) => {
+        let point_a = self.get_capsule_point_closest_to(other);
+        let point_b = other.get_capsule_point_closest_to(self);
+        circle_vs_circle(point_a, r_a, point_b, r_b)
+      },
+      (CdShape::Capsule { radius, .. }, _) =>
+        CdBounds::new_circle(self.get_capsule_point_closest_to(other), radius).contact(other),
+      (_, CdShape::Capsule { .. }) =>
+        other.contact(self).map(Contact::flipped),
+    }
+  }
+}
+
+pub struct SpatialGrid {
+  cell_size: f32,
+  cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+  pub fn new(cell_size: f32) -> Self {
+    Self {
+      cell_size,
+      cells: HashMap::new(),
+    }
+  }
+
+  pub fn clear(&mut self) {
+    for cell in self.cells.values_mut() {
+      cell.clear();
+    }
+  }
+
+  fn get_cell_range(&self, rect: &Rect) -> (i32, i32, i32, i32) {
+    (
+      (rect.left() / self.cell_size).floor() as i32,
+      (rect.right() / self.cell_size).floor() as i32,
+      (rect.top() / self.cell_size).floor() as i32,
+      (rect.bottom() / self.cell_size).floor() as i32,
+    )
+  }
+
+  pub fn insert(&mut self, index: usize, rect: &Rect) {
+    let (left, right, top, bottom) = self.get_cell_range(rect);
+    for x in left..=right {
+      for y in top..=bottom {
+        self.cells.entry((x, y)).or_default().push(index);
+      }
+    }
   }
 
-  pub fn collide_with(&self, other: &CdBounds) -> bool {
-    let act_collided = self.get_rect().overlaps(&other.get_rect());
-    act_collided
+  pub fn query(&self, rect: &Rect, result: &mut Vec<usize>) {
+    result.clear();
+    let (left, right, top, bottom) = self.get_cell_range(rect);
+    for x in left..=right {
+      for y in top..=bottom {
+        if let Some(cell) = self.cells.get(&(x, y)) {
+          result.extend_from_slice(cell);
+        }
+      }
+    }
+    result.sort_unstable();
+    result.dedup();
+  }
+}
+
+impl Snapshot for CdBounds {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_vec2(&self.position);
+    writer.write(self.layer);
+    writer.write(self.mask);
+    match self.shape {
+      CdShape::Aabb { w, h } => {
+        writer.write("aabb");
+        writer.write(w);
+        writer.write(h);
+      },
+      CdShape::Circle { radius } => {
+        writer.write("circle");
+        writer.write(radius);
+      },
+      CdShape::Capsule { half_length, radius, rotation } => {
+        writer.write("capsule");
+        writer.write(half_length);
+        writer.write(radius);
+        writer.write(rotation);
+      },
+    }
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let position = reader.read_vec2()?;
+    let (layer, mask) = (reader.read()?, reader.read()?);
+    let bounds = match reader.read::<String>()?.as_str() {
+      "aabb" => Self::new(position, reader.read()?, reader.read()?),
+      "circle" => Self::new_circle(position, reader.read()?),
+      "capsule" => Self::new_capsule(position, reader.read()?, reader.read()?, reader.read()?),
+      shape => return Err(format!("invalid collision shape {}", shape)),
+    };
+    Ok(bounds.with_layer(layer, mask))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn aabb_contact() {
+    let a = CdBounds::new(Vec2::new(0., 0.), 20., 30.);
+    let b = CdBounds::new(Vec2::new(15., 2.), 20., 30.);
+    let c = CdBounds::new(Vec2::new(25., 0.), 20., 30.);
+
+    assert_eq!(a.contact(&b), Some(Contact { normal: Vec2::new(-1., 0.), depth: 5. }));
+    assert_eq!(b.contact(&a), Some(Contact { normal: Vec2::new(1., 0.), depth: 5. }));
+    assert_eq!(a.contact(&c), None);
+  }
+
+  #[test]
+  fn circle_contact() {
+    let a = CdBounds::new_circle(Vec2::new(0., 0.), 10.);
+    let b = CdBounds::new_circle(Vec2::new(0., 15.), 10.);
+    let aabb = CdBounds::new(Vec2::new(20., 0.), 20., 20.);
+
+    assert_eq!(a.contact(&b), Some(Contact { normal: Vec2::new(0., -1.), depth: 5. }));
+    assert_eq!(a.contact(&aabb), None);
+
+    let touching = CdBounds::new(Vec2::new(15., 0.), 20., 20.);
+    assert_eq!(a.contact(&touching), Some(Contact { normal: Vec2::new(-1., 0.), depth: 5. }));
+    assert_eq!(touching.contact(&a), Some(Contact { normal: Vec2::new(1., 0.), depth: 5. }));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn layers_and_masks() {
+    let player = CdBounds::new(Vec2::ZERO, 20., 30.).with_layer(LAYER_PLAYER, MASK_PLAYER);
+    let enemy = CdBounds::new(Vec2::ZERO, 20., 30.).with_layer(LAYER_ENEMY, MASK_ENEMY);
+    let player_projectile = CdBounds::new_circle(Vec2::ZERO, 5.).with_layer(LAYER_PLAYER_PROJECTILE, MASK_PLAYER_PROJECTILE);
+    let enemy_projectile = CdBounds::new_circle(Vec2::ZERO, 5.).with_layer(LAYER_ENEMY_PROJECTILE, MASK_ENEMY_PROJECTILE);
+
+    assert!(player.can_collide_with(&enemy));
+    assert!(enemy.can_collide_with(&enemy));
+    assert!(player_projectile.can_collide_with(&enemy));
+    assert!(enemy_projectile.can_collide_with(&player));
+    assert!(!player_projectile.can_collide_with(&player));
+    assert!(!enemy_projectile.can_collide_with(&enemy));
+    assert!(!player_projectile.can_collide_with(&enemy_projectile));
+  }
+
+  #[test]
+  fn capsule_contact_at_diagonal() {
+    let capsule = CdBounds::new_capsule(Vec2::ZERO, 10., 3., std::f32::consts::FRAC_PI_4);
+    let on_axis = CdBounds::new_circle(Vec2::new(10., 10.), 3.);
+    let off_axis = CdBounds::new_circle(Vec2::new(10., -10.), 3.);
+
+    assert!(capsule.contact(&on_axis).is_some());
+    assert!(capsule.contact(&off_axis).is_none());
+    assert!(capsule.get_rect().overlaps(&off_axis.get_rect()));
+
+    let aabb = CdBounds::new(Vec2::new(12., 12.), 6., 6.);
+    let contact = capsule.contact(&aabb).expect("capsule should touch aabb");
+    assert!(contact.normal.x < 0. && contact.normal.y < 0.);
+
+    let other = CdBounds::new_capsule(Vec2::new(0., 5.), 10., 2., 0.);
+    assert!(capsule.contact(&other).is_some());
+    assert!(other.contact(&capsule).is_some());
+  }
+
+  #[test]
+  fn query_finds_overlapping_cells() {
+    let mut grid = SpatialGrid::new(64.);
+    grid.insert(0, &Rect::new(0., 0., 16., 16.));
+    grid.insert(1, &Rect::new(60., 60., 16., 16.));
+    grid.insert(2, &Rect::new(-500., 300., 16., 16.));
+
+    let mut result = vec![];
+    grid.query(&Rect::new(10., 10., 4., 4.), &mut result);
+    assert_eq!(result, vec![0, 1]);
+
+    grid.query(&Rect::new(70., 70., 4., 4.), &mut result);
+    assert_eq!(result, vec![1]);
+
+    grid.query(&Rect::new(-480., 310., 200., 4.), &mut result);
+    assert_eq!(result, vec![2]);
+
+    grid.clear();
+    grid.query(&Rect::new(10., 10., 4., 4.), &mut result);
+    assert!(result.is_empty());
+  }
+}
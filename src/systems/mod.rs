@@ -1,4 +1,7 @@
 pub mod ai;
 pub mod cd;
 pub mod animation;
-pub mod timer;
\ No newline at end of file
+pub mod animator;
+pub mod atlas;
+pub mod timer;
+pub mod rng;
\ No newline at end of file
@@ -0,0 +1,105 @@
+use macroquad::miniquad::date;
+
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
+const DEFAULT_INC: u64 = 1442695040888963407;
+const MULTIPLIER: u64 = 6364136223846793005;
+const SECONDS_PER_DAY: f64 = 60. * 60. * 24.;
+
+pub fn generate_seed() -> u64 {
+  (date::now() * 1000.) as u64
+}
+
+pub fn daily_seed() -> u64 {
+  (date::now() / SECONDS_PER_DAY) as u64
+}
+
+pub trait RandomRange {
+  fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self;
+}
+
+macro_rules! impl_random_range {
+  ($($ty:ty),*) => {
+    $(
+      impl RandomRange for $ty {
+        fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+          let r = rng.next_u32() as f64 / (u32::MAX as f64 + 1.);
+          (low as f64 + (high as f64 - low as f64) * r) as Self
+        }
+      }
+    )*
+  };
+}
+
+impl_random_range!(f32, i32, usize);
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    let mut rng = Self { state: 0 };
+    rng.next_u32();
+    rng.state = rng.state.wrapping_add(seed);
+    rng.next_u32();
+    rng
+  }
+
+  pub fn next_u32(&mut self) -> u32 {
+    let old_state = self.state;
+    self.state = old_state.wrapping_mul(MULTIPLIER).wrapping_add(DEFAULT_INC);
+    let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+    let rot = (old_state >> 59) as u32;
+    xorshifted.rotate_right(rot)
+  }
+
+  pub fn gen_range<T: RandomRange>(&mut self, low: T, high: T) -> T {
+    T::gen_range(self, low, high)
+  }
+
+  pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+    if items.is_empty() {
+      return None;
+    }
+    items.get(self.gen_range::<usize>(0, items.len()))
+  }
+}
+
+impl Snapshot for Rng {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.state);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self { state: reader.read()? })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn same_seed_same_sequence() {
+    let mut rng_a = Rng::new(42);
+    let mut rng_b = Rng::new(42);
+
+    for _ in 0..100 {
+      assert_eq!(rng_a.next_u32(), rng_b.next_u32());
+    }
+  }
+
+  #[test]
+  fn gen_range_in_bounds() {
+    let mut rng = Rng::new(7);
+
+    for _ in 0..1000 {
+      let f = rng.gen_range::<f32>(-2., 3.);
+      assert!((-2. ..3.).contains(&f));
+      let i = rng.gen_range::<i32>(0, 5);
+      assert!((0..5).contains(&i));
+    }
+  }
+}
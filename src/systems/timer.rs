@@ -1,5 +1,7 @@
 use macroquad::prelude::*;
 
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
 
 #[derive(Debug, Clone)]
 pub struct Timer {
@@ -27,6 +29,10 @@ impl Timer {
     self.threshold = threshold;
   }
 
+  pub fn get_progress(&self) -> f32 {
+    (self.act / self.threshold).min(1.)
+  }
+
   pub fn is_over(&self) -> bool {
     self.act > self.threshold
   }
@@ -51,6 +57,24 @@ impl Timer {
   }
 }
 
+impl Snapshot for Timer {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.act);
+    writer.write(self.threshold);
+    writer.write_bool(self.just_over);
+    writer.write_bool(self.repeat);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      act: reader.read()?,
+      threshold: reader.read()?,
+      just_over: reader.read_bool()?,
+      repeat: reader.read_bool()?,
+    })
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
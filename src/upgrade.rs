@@ -0,0 +1,115 @@
+use crate::{world_module::weapon::WeaponKind, player::Player, systems::rng::Rng, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
+
+pub const UPGRADE_CHOICES: usize = 3;
+pub const MAX_WEAPONS: usize = 4;
+pub const MAX_WEAPON_LEVEL: usize = 5;
+
+const WEAPON_KINDS: [WeaponKind; 5] = [WeaponKind::Bolt, WeaponKind::Spread, WeaponKind::Orbit, WeaponKind::Nova, WeaponKind::Beam];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Upgrade {
+  NewWeapon(WeaponKind),
+  WeaponLevel(WeaponKind),
+  MoveSpeed,
+  MaxHp,
+  FireRate,
+}
+
+impl Upgrade {
+  pub fn get_description(&self) -> String {
+    match self {
+      Upgrade::NewWeapon(kind) => format!("New weapon: {}", kind.get_name()),
+      Upgrade::WeaponLevel(kind) => format!("Upgrade {}", kind.get_name()),
+      Upgrade::MoveSpeed => "Move speed +10%".to_owned(),
+      Upgrade::MaxHp => "Max HP +1".to_owned(),
+      Upgrade::FireRate => "Fire rate +15%".to_owned(),
+    }
+  }
+}
+
+pub fn roll_upgrades(player: &Player, rng: &mut Rng, count: usize) -> Vec<Upgrade> {
+  let mut pool = vec![Upgrade::MoveSpeed, Upgrade::MaxHp, Upgrade::FireRate];
+  for kind in WEAPON_KINDS {
+    match player.weapons.iter().find(|w| w.kind == kind) {
+      Some(weapon) if weapon.level < MAX_WEAPON_LEVEL => pool.push(Upgrade::WeaponLevel(kind)),
+      None if player.weapons.len() < MAX_WEAPONS => pool.push(Upgrade::NewWeapon(kind)),
+      _ => (),
+    }
+  }
+
+  let mut upgrades = vec![];
+  while upgrades.len() < count && !pool.is_empty() {
+    let index = rng.gen_range::<usize>(0, pool.len());
+    upgrades.push(pool.remove(index));
+  }
+  upgrades
+}
+
+impl Snapshot for Upgrade {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    match self {
+      Upgrade::NewWeapon(kind) => {
+        writer.write(0);
+        writer.write(*kind as u8);
+      },
+      Upgrade::WeaponLevel(kind) => {
+        writer.write(1);
+        writer.write(*kind as u8);
+      },
+      Upgrade::MoveSpeed => writer.write(2),
+      Upgrade::MaxHp => writer.write(3),
+      Upgrade::FireRate => writer.write(4),
+    }
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    match reader.read::<u8>()? {
+      0 => Ok(Upgrade::NewWeapon(WeaponKind::from_tag(reader.read()?)?)),
+      1 => Ok(Upgrade::WeaponLevel(WeaponKind::from_tag(reader.read()?)?)),
+      2 => Ok(Upgrade::MoveSpeed),
+      3 => Ok(Upgrade::MaxHp),
+      4 => Ok(Upgrade::FireRate),
+      tag => Err(format!("unknown upgrade: {}", tag)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use macroquad::prelude::*;
+
+  use super::*;
+  use crate::world_module::{actor::Actor, weapon::Weapon};
+
+  #[test]
+  fn roll_distinct_upgrades() {
+    let player = Player::new(Actor::new(Vec2::ZERO, 100., 5)).with_weapon(Weapon::new(WeaponKind::Bolt));
+    let mut rng = Rng::new(11);
+
+    for _ in 0..50 {
+      let upgrades = roll_upgrades(&player, &mut rng, UPGRADE_CHOICES);
+      assert_eq!(upgrades.len(), UPGRADE_CHOICES);
+      assert!(!upgrades.contains(&Upgrade::NewWeapon(WeaponKind::Bolt)));
+      for (i, upgrade) in upgrades.iter().enumerate() {
+        assert!(!upgrades[i + 1..].contains(upgrade));
+      }
+    }
+  }
+
+  #[test]
+  fn apply_upgrades() {
+    let mut player = Player::new(Actor::new(Vec2::ZERO, 100., 5)).with_weapon(Weapon::new(WeaponKind::Bolt));
+
+    player.apply_upgrade(&Upgrade::NewWeapon(WeaponKind::Nova));
+    player.apply_upgrade(&Upgrade::WeaponLevel(WeaponKind::Bolt));
+    player.apply_upgrade(&Upgrade::MaxHp);
+    player.apply_upgrade(&Upgrade::MoveSpeed);
+
+    assert_eq!(player.weapons.len(), 2);
+    assert_eq!(player.weapons[0].level, 2);
+    assert_eq!(player.weapons[0].damage, 2);
+    assert_eq!(player.actor.hp.max_hp, 6);
+    assert_eq!(player.actor.hp.act_hp, 6);
+    assert!((player.actor.movable.get_speed() - 110.).abs() < 0.01);
+  }
+}
@@ -9,6 +9,10 @@ pub fn generate_id() -> usize {
   COUNTER.fetch_add(1, Ordering::Relaxed)
 }
 
+pub fn reserve_id(id: usize) {
+  COUNTER.fetch_max(id + 1, Ordering::Relaxed);
+}
+
 pub fn get_vector_rotation(v: &Vec2) -> f32 {
   v.y.atan2(v.x)
 }
@@ -25,6 +29,10 @@ impl ReplaceColors {
     Self { armor, detail, skin, eyes }
   }
 
+  pub fn get_colors(&self) -> [Color; 4] {
+    [self.armor, self.detail, self.skin, self.eyes]
+  }
+
   fn replace(&self, c: Color) -> Color {
     match ((c.r*255.) as u32, (c.g*255.) as u32, (c.b*255.) as u32) {
       (245, 115, 147) => self.armor,
@@ -36,7 +44,7 @@ impl ReplaceColors {
   }
 }
 
-pub fn customize_image(mut image: Image, colors: ReplaceColors) -> Texture2D {
+pub fn customize_image(mut image: Image, colors: &ReplaceColors) -> Texture2D {
   for x in 0..image.width() as u32 {
     for y in 0..image.height() as u32 {
         let c = image.get_pixel(x, y);
@@ -1,71 +1,105 @@
 use macroquad::{prelude::*};
 
-use crate::{systems::{animation::Animation, cd::CdBounds}, utils::generate_id};
+use crate::{systems::{animator::{Animator, AnimState}, cd::{CdBounds, LAYER_ENEMY, MASK_ENEMY}}, utils::{generate_id, reserve_id}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
 
 use super::{movable::Movable, damage::Hp};
 
-fn get_idle_animation() -> Animation {
-  Animation::new(vec![Rect::new(0., 0., 16., 16.)], false)
-}
-
-fn get_walking_animation() -> Animation {
-  Animation::new(
-    vec![
-      Rect::new(0., 0., 16., 16.),
-      Rect::new(16., 0., 16., 16.),
-      Rect::new(0., 0., 16., 16.),
-      Rect::new(32., 0., 16., 16.),
-    ],
-    true
-  )
-}
+pub const DEFAULT_SPRITE: &str = "actor";
+// movement speed at which the walk animation plays at its authored pace
+const WALK_ANIMATION_SPEED: f32 = 100.;
 
 #[derive(Debug, Clone)]
 pub struct Actor {
   id: usize,
-  pub animation: Animation,
+  pub animator: Animator,
   pub movable: Movable,
   pub cd_bounds: CdBounds,
-  pub hp: Hp
+  pub hp: Hp,
+  pub kind: usize,
+  pub score_value: usize,
+  pub xp_value: usize,
 }
 
 impl Actor {
   pub fn new(position: Vec2, speed: f32, health: isize) -> Self {
     Self {
       id: generate_id(),
-      animation: get_idle_animation(),
+      animator: Animator::new(DEFAULT_SPRITE, health),
       movable: Movable::new(position, speed, 0.8),
-      cd_bounds: CdBounds::new(position, 20., 30.),
-      hp: Hp::new(health)
+      cd_bounds: CdBounds::new(position, 20., 30.).with_layer(LAYER_ENEMY, MASK_ENEMY),
+      hp: Hp::new(health),
+      kind: 0,
+      score_value: 1,
+      xp_value: 1,
     }
   }
 
+  pub fn with_kind(mut self, kind: usize, score_value: usize, xp_value: usize) -> Self {
+    self.kind = kind;
+    self.score_value = score_value;
+    self.xp_value = xp_value;
+    self
+  }
+
+  pub fn with_sprite(mut self, sprite: &str) -> Self {
+    self.animator = Animator::new(sprite, self.hp.act_hp);
+    self
+  }
+
+  pub fn with_collision_size(mut self, w: f32, h: f32) -> Self {
+    self.cd_bounds = CdBounds::new(self.movable.position, w, h).with_layer(self.cd_bounds.get_layer(), self.cd_bounds.get_mask());
+    self
+  }
+
   pub fn get_id(&self) -> usize {
     self.id
   }
 
   pub fn get_source(&self) -> Rect {
-    self.animation.get_act_frame()
+    self.animator.get_act_frame()
   }
 
   pub fn is_alive(&self) -> bool {
     self.hp.is_alive()
   }
 
+  pub fn is_hurt(&self) -> bool {
+    self.animator.get_state() == AnimState::Hurt
+  }
+
+  pub fn has_finished_dying(&self) -> bool {
+    self.animator.get_state() == AnimState::Death && self.animator.is_finished()
+  }
+
   pub fn move_to(&mut self, target_position: Vec2) {
-    if !self.movable.is_moving() {
-      self.animation = get_walking_animation();
-    }
     self.movable.set_moving_to(target_position);
+    self.animator.set_moving(self.movable.is_moving());
+  }
+
+  pub fn steer(&mut self, direction: Vec2) {
+    if direction == Vec2::ZERO {
+      if self.movable.target_position.is_none() {
+        self.stop();
+      }
+      return;
+    }
+    self.movable.set_direction(direction);
+    self.animator.set_moving(true);
   }
 
   pub fn stop(&mut self) {
     self.movable.stop();
-    self.animation = get_idle_animation();
+    self.animator.set_moving(false);
+  }
+
+  pub fn attack(&mut self) {
+    self.animator.trigger_attack();
   }
 
   pub fn update(&mut self, delta_t: f32) {
-    self.animation.update(delta_t);
+    self.animator.set_health(self.hp.act_hp);
+    self.animator.set_walk_speed(self.movable.get_speed() / WALK_ANIMATION_SPEED);
+    self.animator.update(delta_t);
     self.movable.update(delta_t);
     self.cd_bounds.update_position(&self.movable.position);
 
@@ -76,6 +110,34 @@ impl Actor {
   }
 }
 
+impl Snapshot for Actor {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.id);
+    writer.write_snapshot(&self.animator);
+    writer.write_snapshot(&self.movable);
+    writer.write_snapshot(&self.cd_bounds);
+    writer.write_snapshot(&self.hp);
+    writer.write(self.kind);
+    writer.write(self.score_value);
+    writer.write(self.xp_value);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let id = reader.read()?;
+    reserve_id(id);
+    Ok(Self {
+      id,
+      animator: reader.read_snapshot()?,
+      movable: reader.read_snapshot()?,
+      cd_bounds: reader.read_snapshot()?,
+      hp: reader.read_snapshot()?,
+      kind: reader.read()?,
+      score_value: reader.read()?,
+      xp_value: reader.read()?,
+    })
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -112,4 +174,31 @@ mod tests {
     assert_eq!(actor.movable.is_moving(), false);
     assert_eq!(actor.get_source(), Rect::new(0., 0., 16., 16.));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn steer() {
+    let mut actor = create();
+
+    actor.steer(Vec2::X);
+    actor.steer(Vec2::ZERO);
+    assert!(!actor.movable.is_moving());
+
+    actor.move_to(Vec2::new(50., 0.));
+    actor.steer(Vec2::ZERO);
+    assert!(actor.movable.is_moving());
+    assert_eq!(actor.movable.target_position, Some(Vec2::new(50., 0.)));
+  }
+
+  #[test]
+  fn dying() {
+    let mut actor = create();
+
+    actor.hp.modify(1, 1, -2);
+    actor.update(0.01);
+    assert!(!actor.has_finished_dying());
+    for _ in 0..100 {
+      actor.update(0.01);
+    }
+    assert!(actor.has_finished_dying());
+  }
+}
@@ -1,5 +1,7 @@
 use macroquad::{prelude::*};
 
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
 #[derive(Debug, Clone, Copy)]
 pub struct HpModification {
   source: usize,
@@ -25,12 +27,14 @@ impl HpModification {
 pub struct Hp {
   modifications: Vec<HpModification>,
   pub act_hp: isize,
+  pub max_hp: isize,
 }
 
 impl Hp {
     pub fn new(initial_hp: isize) -> Self {
       Self {
         act_hp: initial_hp,
+        max_hp: initial_hp,
         modifications: vec![HpModification::new(0, 0, initial_hp)],
       }
     }
@@ -44,11 +48,48 @@ impl Hp {
       self.act_hp += amount;
     }
 
+    pub fn increase_max(&mut self, amount: isize) {
+      self.max_hp += amount;
+      self.act_hp += amount;
+    }
+
     pub fn get_last_modification(&self) -> Option<HpModification> {
       self.modifications.last().and_then(|m| Some(*m))
     }
 
+    pub fn get_modifications(&self) -> &Vec<HpModification> {
+      &self.modifications
+    }
+
     pub fn has_been_modified_by_source(&self, source: usize) -> bool {
       self.modifications.iter().any(|hp_mod| hp_mod.is_of_source(source))
     }
-}
\ No newline at end of file
+}
+
+impl Snapshot for HpModification {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.source);
+    writer.write(self.source_origin);
+    writer.write(self.amount);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self::new(reader.read()?, reader.read()?, reader.read()?))
+  }
+}
+
+impl Snapshot for Hp {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_list(&self.modifications);
+    writer.write(self.act_hp);
+    writer.write(self.max_hp);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      modifications: reader.read_list()?,
+      act_hp: reader.read()?,
+      max_hp: reader.read()?,
+    })
+  }
+}
@@ -0,0 +1,223 @@
+use macroquad::prelude::*;
+
+use crate::{config::{parse_config, parse_color, ConfigSection}, systems::{ai::{Ai, WeightedStates}, animator::ANIM_STATES, atlas::Atlas, rng::Rng}, utils::ReplaceColors};
+
+use super::actor::Actor;
+
+pub const DEFAULT_ENEMIES: &str = include_str!("../../assets/enemies.ini");
+
+fn scale(base: f32, per_difficulty: f32, difficulty: usize) -> f32 {
+  (base + per_difficulty * difficulty as f32).max(0.)
+}
+
+fn read_four<T: std::str::FromStr + Clone>(section: &ConfigSection, key: &str, default: &[T; 4]) -> Result<[T; 4], String> {
+  let values = section.get_list_or(key, default)?;
+  <[T; 4]>::try_from(values).map_err(|_| format!("[{}] {} should have 4 values", section.name, key))
+}
+
+#[derive(Debug, Clone)]
+pub struct EnemyArchetype {
+  pub name: String,
+  pub speed: f32,
+  pub speed_per_difficulty: f32,
+  pub hp: isize,
+  pub size: (f32, f32),
+  pub ai_weights: [f32; 4],
+  pub ai_weights_per_difficulty: [f32; 4],
+  pub sprite: String,
+  pub palette: [Color; 4],
+  pub score: usize,
+  pub xp: usize,
+  pub spawn_weight: f32,
+  pub spawn_weight_per_difficulty: f32,
+  pub min_difficulty: usize,
+}
+
+impl EnemyArchetype {
+  fn from_section(section: &ConfigSection) -> Result<Self, String> {
+    let size = section.get_list_or::<f32>("size", &[20., 30.])?;
+    let sprite: String = section.require("sprite")?;
+    if sprite.contains(char::is_whitespace) {
+      return Err(format!("[{}] sprite should be a single atlas tag prefix", section.name));
+    }
+    let palette = read_four::<String>(section, "palette", &[
+      "399f32".to_owned(), "42691d".to_owned(), "429f32".to_owned(), "000000".to_owned()
+    ])?;
+
+    Ok(Self {
+      name: section.name.clone(),
+      speed: section.require("speed")?,
+      speed_per_difficulty: section.get_or("speed_per_difficulty", 0.)?,
+      hp: section.require("hp")?,
+      size: match size.as_slice() {
+        [w, h] => (*w, *h),
+        _ => return Err(format!("[{}] size should be 2 numbers", section.name)),
+      },
+      ai_weights: read_four(section, "ai_weights", &[1., 5., 7., 0.])?,
+      ai_weights_per_difficulty: read_four(section, "ai_weights_per_difficulty", &[0.; 4])?,
+      sprite,
+      palette: [
+        parse_color(&palette[0])?,
+        parse_color(&palette[1])?,
+        parse_color(&palette[2])?,
+        parse_color(&palette[3])?,
+      ],
+      score: section.get_or("score", 1)?,
+      xp: section.get_or("xp", 1)?,
+      spawn_weight: section.get_or("spawn_weight", 1.)?,
+      spawn_weight_per_difficulty: section.get_or("spawn_weight_per_difficulty", 0.)?,
+      min_difficulty: section.get_or("min_difficulty", 0)?,
+    })
+  }
+
+  pub fn get_colors(&self) -> ReplaceColors {
+    ReplaceColors::new(self.palette[0], self.palette[1], self.palette[2], self.palette[3])
+  }
+
+  pub fn get_spawn_weight(&self, difficulty: usize) -> i32 {
+    if difficulty < self.min_difficulty {
+      return 0;
+    }
+    scale(self.spawn_weight, self.spawn_weight_per_difficulty, difficulty) as i32
+  }
+
+  pub fn get_ai_weights(&self, difficulty: usize) -> [i32; 4] {
+    let mut weights = [0; 4];
+    for (i, weight) in weights.iter_mut().enumerate() {
+      *weight = scale(self.ai_weights[i], self.ai_weights_per_difficulty[i], difficulty) as i32;
+    }
+    weights
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnemyTable {
+  pub archetypes: Vec<EnemyArchetype>,
+}
+
+impl EnemyTable {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let archetypes = parse_config(data)?
+      .iter()
+      .map(EnemyArchetype::from_section)
+      .collect::<Result<Vec<EnemyArchetype>, String>>()?;
+
+    if archetypes.is_empty() {
+      return Err("no enemy archetypes defined".to_owned());
+    }
+    for (i, archetype) in archetypes.iter().enumerate() {
+      if archetypes[..i].iter().any(|a| a.name == archetype.name) {
+        return Err(format!("duplicate enemy archetype: {}", archetype.name));
+      }
+    }
+    Ok(Self { archetypes })
+  }
+
+  pub fn validate_sprites(&self, atlas: &Atlas) -> Result<(), String> {
+    for archetype in &self.archetypes {
+      for state in ANIM_STATES.iter().filter(|s| !s.is_optional()) {
+        let tag = format!("{}_{}", archetype.sprite, state.get_name());
+        if !atlas.has_tag(&tag) {
+          return Err(format!("[{}] sprite atlas has no tag {}", archetype.name, tag));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  pub fn find(&self, name: &str) -> Option<usize> {
+    self.archetypes.iter().position(|a| a.name == name)
+  }
+
+  pub fn choose(&self, difficulty: usize, rng: &mut Rng) -> Option<usize> {
+    let total: i32 = self.archetypes.iter().map(|a| a.get_spawn_weight(difficulty)).sum();
+    if total <= 0 {
+      return None;
+    }
+
+    let mut result = rng.gen_range::<i32>(0, total);
+    for (kind, archetype) in self.archetypes.iter().enumerate() {
+      let weight = archetype.get_spawn_weight(difficulty);
+      if result < weight {
+        return Some(kind);
+      }
+      result -= weight;
+    }
+    None
+  }
+
+  pub fn spawn(&self, kind: usize, position: Vec2, difficulty: usize, rng: &mut Rng) -> (Actor, Ai) {
+    let archetype = &self.archetypes[kind];
+    let actor = Actor::new(position, scale(archetype.speed, archetype.speed_per_difficulty, difficulty), archetype.hp)
+      .with_kind(kind, archetype.score, archetype.xp)
+      .with_sprite(&archetype.sprite)
+      .with_collision_size(archetype.size.0, archetype.size.1);
+    let ai = Ai::new(WeightedStates::new_idle_wandering_attacking(&archetype.get_ai_weights(difficulty)), rng);
+    (actor, ai)
+  }
+}
+
+impl Default for EnemyTable {
+  fn default() -> Self {
+    Self::parse(DEFAULT_ENEMIES).expect("assets/enemies.ini should be valid")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn default_table_is_valid() {
+    let table = EnemyTable::default();
+    let goblin = &table.archetypes[table.find("goblin").expect("goblin should be defined")];
+
+    assert_eq!(goblin.get_ai_weights(5), [1, 5, 12, 2]);
+    assert_eq!(goblin.get_spawn_weight(0), 10);
+    assert!(table.validate_sprites(&Atlas::default()).is_ok());
+  }
+
+  #[test]
+  fn validate_requires_only_idle_and_walk() {
+    let table = EnemyTable::parse("[slime]\nspeed = 10\nhp = 1\nsprite = slime").expect("table should be parsed");
+    let atlas = |tags: &str| Atlas::parse(&format!(
+      r#"{{ "frames": [{{ "frame": {{ "x": 0, "y": 0, "w": 8, "h": 8 }}, "duration": 100 }}], "meta": {{ "frameTags": [{}] }} }}"#,
+      tags
+    )).expect("atlas should be parsed");
+
+    assert!(table.validate_sprites(&atlas(r#"{ "name": "slime_idle", "from": 0, "to": 0 }, { "name": "slime_walk", "from": 0, "to": 0 }"#)).is_ok());
+    assert!(table.validate_sprites(&atlas(r#"{ "name": "slime_idle", "from": 0, "to": 0 }"#)).is_err());
+  }
+
+  #[test]
+  fn choose_respects_min_difficulty() {
+    let table = EnemyTable::default();
+    let brute = table.find("brute").expect("brute should be defined");
+    let mut rng = Rng::new(3);
+
+    assert!((0..200).all(|_| table.choose(0, &mut rng) != Some(brute)));
+    assert!((0..200).any(|_| table.choose(20, &mut rng) == Some(brute)));
+  }
+
+  #[test]
+  fn spawn_uses_archetype() {
+    let table = EnemyTable::parse("
+      [slime]
+      speed = 10
+      speed_per_difficulty = 2
+      hp = 4
+      size = 12 8
+      sprite = enemy
+      score = 5
+    ").expect("table should be parsed");
+    let (actor, _) = table.spawn(0, Vec2::ZERO, 3, &mut Rng::new(1));
+
+    assert_eq!(actor.hp.act_hp, 4);
+    assert_eq!(actor.score_value, 5);
+    assert_eq!(actor.cd_bounds.get_rect(), Rect::new(-6., -4., 12., 8.));
+    assert_eq!(actor.get_source(), Rect::new(48., 0., 16., 16.));
+    assert!(EnemyTable::parse("[slime]\nspeed = 1\nhp = 1\nsprite = enemy walk").is_err());
+    assert!(EnemyTable::parse("[slime]\nhp = 4").is_err());
+    assert!(EnemyTable::parse("[a]\nspeed = 1\nhp = 1\nsprite = enemy\n[a]\nspeed = 1\nhp = 1\nsprite = enemy").is_err());
+  }
+}
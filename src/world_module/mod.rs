@@ -3,4 +3,8 @@ pub mod projectile;
 pub mod actor;
 pub mod particle;
 pub mod world;
-pub mod damage;
\ No newline at end of file
+pub mod damage;
+pub mod enemy;
+pub mod weapon;
+pub mod pickup;
+pub mod stats;
\ No newline at end of file
@@ -1,7 +1,7 @@
 
 use macroquad::{prelude::*};
 
-use crate::utils::get_vector_rotation;
+use crate::{utils::get_vector_rotation, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
 
 const EPSILON: f32 = 0.004;
 
@@ -35,6 +35,15 @@ impl Movable {
     self
   }
 
+  pub fn get_speed(&self) -> f32 {
+    self.speed
+  }
+
+  pub fn set_speed(&mut self, speed: f32) {
+    self.velocity = self.velocity.normalize_or_zero() * speed;
+    self.speed = speed;
+  }
+
   pub fn add_impuls(&mut self, implus: Vec2) {
     self.impuls += implus;
   }
@@ -48,6 +57,14 @@ impl Movable {
     self.target_position = Some(target_position);
   }
 
+  pub fn set_direction(&mut self, direction: Vec2) {
+    self.velocity = direction.clamp_length_max(1.) * self.speed;
+    self.target_position = None;
+    if direction != Vec2::ZERO {
+      self.rotation = get_vector_rotation(&self.velocity);
+    }
+  }
+
   pub fn set_to_target_position(&mut self) {
     if let Some(tp) = self.target_position {
       self.position = tp;
@@ -74,4 +91,32 @@ impl Movable {
     self.impuls = if new_imp.length_squared() > EPSILON { new_imp } else { Vec2::ZERO };
     self.position += (self.velocity + self.impuls) * delta_t;
   }
-}
\ No newline at end of file
+}
+
+impl Snapshot for Movable {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_vec2(&self.position);
+    writer.write_bool(self.target_position.is_some());
+    writer.write_vec2(&self.target_position.unwrap_or(Vec2::ZERO));
+    writer.write_vec2(&self.velocity);
+    writer.write_vec2(&self.impuls);
+    writer.write(self.fraction);
+    writer.write(self.rotation);
+    writer.write(self.speed);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let position = reader.read_vec2()?;
+    let has_target = reader.read_bool()?;
+    let target_position = reader.read_vec2()?;
+    Ok(Self {
+      position,
+      target_position: if has_target { Some(target_position) } else { None },
+      velocity: reader.read_vec2()?,
+      impuls: reader.read_vec2()?,
+      fraction: reader.read()?,
+      rotation: reader.read()?,
+      speed: reader.read()?,
+    })
+  }
+}
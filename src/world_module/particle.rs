@@ -1,6 +1,8 @@
 use macroquad::prelude::*;
 
-use crate::systems::{animation::Animation, timer::Timer};
+use std::f32::consts::TAU;
+
+use crate::{systems::{animation::Animation, atlas::get_atlas, timer::Timer}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
 
 use super::movable::Movable;
 
@@ -14,10 +16,10 @@ pub struct Particle {
 }
 
 impl Particle {
-  pub fn new(pos: Vec2, vel: Vec2, frames: Vec<Rect>, ttl: f32) -> Self {
+  pub fn new(pos: Vec2, vel: Vec2, animation: Animation, ttl: f32) -> Self {
     Self {
       movable: Movable::new(pos, 50., 0.).with_velocity(vel),
-      animation: Animation::new(frames, false),
+      animation,
       timeout: Timer::new_timeout(ttl)
     }
   }
@@ -47,6 +49,13 @@ impl ParticleSystem {
     self.particles.push(particle);
   }
 
+  pub fn add_burst(&mut self, position: Vec2, count: usize, tag: &str, ttl: f32) {
+    for i in 0..count {
+      let direction = Vec2::from_angle(i as f32 * TAU / count as f32);
+      self.add_particle(Particle::new(position, direction, get_atlas().get_animation(tag, false), ttl));
+    }
+  }
+
   pub fn update(&mut self, delta_t: f32) {
     let new_particles: Vec<Particle> = self.particles.clone();
     self.particles.clear();
@@ -58,4 +67,30 @@ impl ParticleSystem {
       }
     }
   }
-}
\ No newline at end of file
+}
+
+impl Snapshot for Particle {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_snapshot(&self.movable);
+    writer.write_snapshot(&self.animation);
+    writer.write_snapshot(&self.timeout);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      movable: reader.read_snapshot()?,
+      animation: reader.read_snapshot()?,
+      timeout: reader.read_snapshot()?,
+    })
+  }
+}
+
+impl Snapshot for ParticleSystem {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_list(&self.particles);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self { particles: reader.read_list()? })
+  }
+}
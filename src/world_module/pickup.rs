@@ -0,0 +1,214 @@
+use macroquad::prelude::*;
+
+use crate::{config::{parse_config, ConfigSection}, systems::{cd::{CdBounds, LAYER_PICKUP, MASK_PICKUP}, rng::Rng, timer::Timer}, utils::{generate_id, reserve_id}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
+
+use super::movable::Movable;
+
+pub const DEFAULT_PICKUPS: &str = include_str!("../../assets/pickups.ini");
+
+const PICKUP_RADIUS: f32 = 8.;
+const DROP_SCATTER: f32 = 12.;
+const EXPIRING_PROGRESS: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PickupKind {
+  Health,
+  Experience,
+  Magnet,
+  Bomb,
+}
+
+const PICKUP_KINDS: [PickupKind; 4] = [PickupKind::Health, PickupKind::Experience, PickupKind::Magnet, PickupKind::Bomb];
+
+impl PickupKind {
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      PickupKind::Health => "health",
+      PickupKind::Experience => "experience",
+      PickupKind::Magnet => "magnet",
+      PickupKind::Bomb => "bomb",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Pickup {
+  id: usize,
+  pub kind: PickupKind,
+  pub value: usize,
+  pub movable: Movable,
+  pub cd_bounds: CdBounds,
+  pub is_alive: bool,
+  pub attracted: bool,
+  lifetime: Timer,
+}
+
+impl Pickup {
+  pub fn new(kind: PickupKind, value: usize, position: Vec2, lifetime: f32) -> Self {
+    Self {
+      id: generate_id(),
+      kind,
+      value,
+      movable: Movable::new(position, 0., 1.),
+      cd_bounds: CdBounds::new_circle(position, PICKUP_RADIUS).with_layer(LAYER_PICKUP, MASK_PICKUP),
+      is_alive: true,
+      attracted: false,
+      lifetime: Timer::new_timeout(lifetime),
+    }
+  }
+
+  pub fn get_id(&self) -> usize {
+    self.id
+  }
+
+  pub fn is_expiring(&self) -> bool {
+    self.lifetime.get_progress() > EXPIRING_PROGRESS
+  }
+
+  pub fn update(&mut self, delta_t: f32, player_position: &Vec2, drops: &DropTable) {
+    self.lifetime.update(delta_t);
+    if self.lifetime.is_over() {
+      self.is_alive = false;
+      return;
+    }
+
+    let to_player = *player_position - self.movable.position;
+    if to_player.length_squared() < drops.attract_radius * drops.attract_radius {
+      self.attracted = true;
+    }
+    if self.attracted {
+      let step = (drops.attract_speed * delta_t).min(to_player.length());
+      self.movable.position += to_player.normalize_or_zero() * step;
+      self.cd_bounds.update_position(&self.movable.position);
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTable {
+  pub lifetime: f32,
+  pub attract_radius: f32,
+  pub attract_speed: f32,
+  pub chances: [f32; 4],
+  pub health_amount: usize,
+  pub bomb_radius: f32,
+}
+
+impl DropTable {
+  pub fn parse(data: &str) -> Result<Self, String> {
+    let sections = parse_config(data)?;
+    let empty = ConfigSection::new("");
+    let section = |name: &str| sections.iter().find(|s| s.name == name).unwrap_or(&empty);
+
+    let mut chances = [0.; 4];
+    for (chance, kind) in chances.iter_mut().zip(PICKUP_KINDS) {
+      *chance = section(kind.get_name()).get_or("chance", 0.)?;
+      if !(0. ..=1.).contains(chance) {
+        return Err(format!("[{}] chance should be between 0 and 1", kind.get_name()));
+      }
+    }
+
+    let drops = section("drops");
+    Ok(Self {
+      lifetime: drops.get_or("lifetime", 20.)?,
+      attract_radius: drops.get_or("attract_radius", 90.)?,
+      attract_speed: drops.get_or("attract_speed", 320.)?,
+      chances,
+      health_amount: section("health").get_or("amount", 1)?,
+      bomb_radius: section("bomb").get_or("radius", 420.)?,
+    })
+  }
+
+  pub fn get_chance(&self, kind: PickupKind) -> f32 {
+    self.chances[kind as usize]
+  }
+
+  pub fn roll(&self, position: Vec2, xp: usize, rng: &mut Rng) -> Vec<Pickup> {
+    let mut pickups = vec![];
+    for kind in PICKUP_KINDS {
+      if rng.gen_range::<f32>(0., 1.) >= self.get_chance(kind) {
+        continue;
+      }
+      let value = match kind {
+        PickupKind::Health => self.health_amount,
+        PickupKind::Experience => xp,
+        PickupKind::Magnet | PickupKind::Bomb => 0,
+      };
+      let offset = Vec2::new(rng.gen_range::<f32>(-DROP_SCATTER, DROP_SCATTER), rng.gen_range::<f32>(-DROP_SCATTER, DROP_SCATTER));
+      pickups.push(Pickup::new(kind, value, position + offset, self.lifetime));
+    }
+    pickups
+  }
+}
+
+impl Default for DropTable {
+  fn default() -> Self {
+    Self::parse(DEFAULT_PICKUPS).expect("assets/pickups.ini should be valid")
+  }
+}
+
+impl Snapshot for Pickup {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.id);
+    writer.write(self.kind as u8);
+    writer.write(self.value);
+    writer.write_snapshot(&self.movable);
+    writer.write_snapshot(&self.cd_bounds);
+    writer.write_bool(self.is_alive);
+    writer.write_bool(self.attracted);
+    writer.write_snapshot(&self.lifetime);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let id = reader.read()?;
+    reserve_id(id);
+    let tag = reader.read::<u8>()?;
+    Ok(Self {
+      id,
+      kind: *PICKUP_KINDS.get(tag as usize).ok_or_else(|| format!("unknown pickup kind: {}", tag))?,
+      value: reader.read()?,
+      movable: reader.read_snapshot()?,
+      cd_bounds: reader.read_snapshot()?,
+      is_alive: reader.read_bool()?,
+      attracted: reader.read_bool()?,
+      lifetime: reader.read_snapshot()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_drop_table() {
+    let drops = DropTable::parse("[drops]\nlifetime = 5\n[experience]\nchance = 1\n[bomb]\nchance = 0.5\nradius = 100").unwrap();
+
+    assert_eq!(drops.lifetime, 5.);
+    assert_eq!(drops.get_chance(PickupKind::Experience), 1.);
+    assert_eq!(drops.get_chance(PickupKind::Health), 0.);
+    assert_eq!(drops.bomb_radius, 100.);
+    assert!(DropTable::parse("[magnet]\nchance = 2").is_err());
+
+    let pickups = drops.roll(Vec2::ZERO, 3, &mut Rng::new(5));
+    assert_eq!(pickups[0].kind, PickupKind::Experience);
+    assert_eq!(pickups[0].value, 3);
+  }
+
+  #[test]
+  fn attract_and_expire() {
+    let drops = DropTable { lifetime: 1., attract_radius: 50., attract_speed: 100., ..DropTable::default() };
+    let mut pickup = Pickup::new(PickupKind::Experience, 1, Vec2::new(40., 0.), drops.lifetime);
+
+    pickup.update(0.1, &Vec2::new(200., 0.), &drops);
+    assert!(!pickup.attracted);
+    assert_eq!(pickup.movable.position, Vec2::new(40., 0.));
+
+    pickup.update(0.1, &Vec2::ZERO, &drops);
+    assert!(pickup.attracted);
+    assert!((pickup.movable.position.x - 30.).abs() < 0.01);
+
+    pickup.update(1., &Vec2::ZERO, &drops);
+    assert!(!pickup.is_alive);
+  }
+}
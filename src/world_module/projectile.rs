@@ -1,15 +1,18 @@
+use std::f32::consts::PI;
+
 use macroquad::{prelude::*};
-use crate::{systems::{animation::Animation, cd::CdBounds, timer::Timer}, utils::generate_id};
-use super::{movable::Movable, actor::Actor};
+use crate::{systems::{animation::Animation, atlas::get_atlas, cd::{CdBounds, LAYER_PLAYER_PROJECTILE, MASK_PLAYER_PROJECTILE}, timer::Timer}, utils::{generate_id, reserve_id, get_vector_rotation}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
+use super::{movable::Movable, actor::Actor, weapon::WeaponKind};
 
 
 fn get_flying_animation() -> Animation {
-  Animation::new(
-    vec![
-      Rect::new(0., 0., 16., 16.),
-    ],
-    false
-  )
+  get_atlas().get_animation("fireball", false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+  Linear,
+  Orbit { radius: f32, angle: f32, angular_speed: f32 },
 }
 
 #[derive(Debug, Clone)]
@@ -20,7 +23,13 @@ pub struct Projectile {
   pub animation: Animation,
   pub cd_bounds: CdBounds,
   pub is_alive: bool,
+  pub has_hit: bool,
   pub particles_timer: Timer,
+  pub damage: isize,
+  pub pierce: usize,
+  pub motion: Motion,
+  pub weapon: Option<WeaponKind>,
+  lifetime: Option<Timer>,
 }
 
 impl Projectile {
@@ -30,34 +39,175 @@ impl Projectile {
       origin_id,
       movable: Movable::new(position, 150., 1.).with_velocity(velocity),
       animation: get_flying_animation(),
-      cd_bounds: CdBounds::new(position, 16., 16.),
+      cd_bounds: CdBounds::new_capsule(position, 4., 5., get_vector_rotation(&velocity)).with_layer(LAYER_PLAYER_PROJECTILE, MASK_PLAYER_PROJECTILE),
       is_alive: true,
-      particles_timer: Timer::new(0.05)
+      has_hit: false,
+      particles_timer: Timer::new(0.05),
+      damage: 1,
+      pierce: 0,
+      motion: Motion::Linear,
+      weapon: None,
+      lifetime: None,
     }
   }
 
-  pub fn apply(&mut self, actor: &mut Actor) {
-    if !actor.hp.has_been_modified_by_source(self.id) {
-      actor.hp.modify(self.id, self.origin_id, -1);
+  pub fn with_damage(mut self, damage: isize) -> Self {
+    self.damage = damage;
+    self
+  }
+
+  pub fn with_weapon(mut self, weapon: WeaponKind) -> Self {
+    self.weapon = Some(weapon);
+    self
+  }
+
+  pub fn with_pierce(mut self, pierce: usize) -> Self {
+    self.pierce = pierce;
+    self
+  }
+
+  pub fn with_lifetime(mut self, lifetime: f32) -> Self {
+    self.lifetime = Some(Timer::new_timeout(lifetime));
+    self
+  }
+
+  pub fn with_speed(mut self, speed: f32) -> Self {
+    self.movable.velocity = self.movable.velocity.normalize_or_zero() * speed;
+    self
+  }
+
+  pub fn with_size(mut self, half_length: f32, radius: f32) -> Self {
+    self.cd_bounds = CdBounds::new_capsule(self.movable.position, half_length, radius, self.movable.rotation)
+      .with_layer(self.cd_bounds.get_layer(), self.cd_bounds.get_mask());
+    self
+  }
+
+  pub fn with_orbit(mut self, radius: f32, angle: f32, angular_speed: f32) -> Self {
+    self.motion = Motion::Orbit { radius, angle, angular_speed };
+    self.movable.velocity = Vec2::ZERO;
+    self
+  }
+
+  pub fn with_collision_layer(mut self, layer: u32, mask: u32) -> Self {
+    self.cd_bounds.set_layer(layer, mask);
+    self
+  }
+
+  pub fn get_id(&self) -> usize {
+    self.id
+  }
+
+  pub fn apply(&mut self, actor: &mut Actor) -> bool {
+    if actor.hp.has_been_modified_by_source(self.id) {
+      return false;
     }
-    self.is_alive = false;
+    actor.hp.modify(self.id, self.origin_id, -self.damage);
+    if self.pierce == 0 {
+      self.is_alive = false;
+    } else {
+      self.pierce -= 1;
+    }
+    true
   }
 
   pub fn get_source(&self) -> Rect {
     self.animation.get_act_frame()
   }
 
-  pub fn update(&mut self, delta_t: f32) {
+  pub fn update(&mut self, delta_t: f32, anchor: &Vec2) {
     self.animation.update(delta_t);
-    self.movable.update(delta_t);
+    match &mut self.motion {
+      Motion::Linear => self.movable.update(delta_t),
+      Motion::Orbit { radius, angle, angular_speed } => {
+        *angle += *angular_speed * delta_t;
+        self.movable.position = *anchor + Vec2::new(angle.cos(), angle.sin()) * *radius;
+        self.movable.rotation = *angle + PI / 2.;
+      },
+    }
+    if let Some(lifetime) = &mut self.lifetime {
+      lifetime.update(delta_t);
+      if lifetime.is_over() {
+        self.is_alive = false;
+      }
+    }
     self.particles_timer.update(delta_t);
     self.cd_bounds.update_position(&self.movable.position);
+    self.cd_bounds.update_rotation(self.movable.rotation);
   }
 }
 
+pub fn spawn_projectile_in_direction(origin_id: usize, position_from: &Vec2, direction: Vec2) -> Projectile {
+  let position = *position_from + (direction * 32.);
+
+  Projectile::new(origin_id, position, direction)
+}
+
 pub fn spawn_projectile_from_actor(origin_id: usize, position_from: &Vec2, position_to: &Vec2) -> Projectile {
-  let velocity = (*position_to - *position_from).normalize();
-  let position = *position_from + (velocity * 32.);
+  spawn_projectile_in_direction(origin_id, position_from, (*position_to - *position_from).normalize())
+}
+
+impl Snapshot for Motion {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    match self {
+      Motion::Linear => writer.write(0),
+      Motion::Orbit { radius, angle, angular_speed } => {
+        writer.write(1);
+        writer.write(radius);
+        writer.write(angle);
+        writer.write(angular_speed);
+      },
+    }
+  }
 
-  Projectile::new(origin_id, position, velocity)
-}
\ No newline at end of file
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    match reader.read::<u8>()? {
+      0 => Ok(Motion::Linear),
+      1 => Ok(Motion::Orbit { radius: reader.read()?, angle: reader.read()?, angular_speed: reader.read()? }),
+      tag => Err(format!("unknown projectile motion: {}", tag)),
+    }
+  }
+}
+
+impl Snapshot for Projectile {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.id);
+    writer.write(self.origin_id);
+    writer.write_snapshot(&self.movable);
+    writer.write_snapshot(&self.animation);
+    writer.write_snapshot(&self.cd_bounds);
+    writer.write_bool(self.is_alive);
+    writer.write_bool(self.has_hit);
+    writer.write_snapshot(&self.particles_timer);
+    writer.write(self.damage);
+    writer.write(self.pierce);
+    writer.write_snapshot(&self.motion);
+    writer.write_bool(self.weapon.is_some());
+    if let Some(weapon) = self.weapon {
+      writer.write(weapon as u8);
+    }
+    writer.write_bool(self.lifetime.is_some());
+    if let Some(lifetime) = &self.lifetime {
+      writer.write_snapshot(lifetime);
+    }
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let id = reader.read()?;
+    reserve_id(id);
+    Ok(Self {
+      id,
+      origin_id: reader.read()?,
+      movable: reader.read_snapshot()?,
+      animation: reader.read_snapshot()?,
+      cd_bounds: reader.read_snapshot()?,
+      is_alive: reader.read_bool()?,
+      has_hit: reader.read_bool()?,
+      particles_timer: reader.read_snapshot()?,
+      damage: reader.read()?,
+      pierce: reader.read()?,
+      motion: reader.read_snapshot()?,
+      weapon: if reader.read_bool()? { Some(WeaponKind::from_tag(reader.read()?)?) } else { None },
+      lifetime: if reader.read_bool()? { Some(reader.read_snapshot()?) } else { None },
+    })
+  }
+}
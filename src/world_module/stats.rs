@@ -0,0 +1,162 @@
+use crate::snapshot::{Snapshot, SnapshotWriter, SnapshotReader};
+
+use super::{damage::Hp, weapon::WeaponKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KillSource {
+  Weapon(WeaponKind),
+  Bomb,
+  OutOfBounds,
+  Other,
+}
+
+impl KillSource {
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      KillSource::Weapon(kind) => kind.get_name(),
+      KillSource::Bomb => "bomb",
+      KillSource::OutOfBounds => "out of bounds",
+      KillSource::Other => "other",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DamageSource {
+  Contact,
+  Projectile,
+}
+
+impl DamageSource {
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      DamageSource::Contact => "contact",
+      DamageSource::Projectile => "projectiles",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+  pub time: f32,
+  pub kills: Vec<(KillSource, usize)>,
+  pub projectiles_fired: usize,
+  pub projectiles_hit: usize,
+  pub damage_taken: Vec<(DamageSource, isize)>,
+  pub distance_walked: f32,
+  pub max_difficulty: usize,
+}
+
+impl RunStats {
+  pub fn add_kill(&mut self, source: KillSource) {
+    match self.kills.iter_mut().find(|(s, _)| *s == source) {
+      Some((_, count)) => *count += 1,
+      None => self.kills.push((source, 1)),
+    }
+  }
+
+  pub fn get_total_kills(&self) -> usize {
+    self.kills.iter().map(|(_, count)| count).sum()
+  }
+
+  pub fn get_accuracy(&self) -> f32 {
+    if self.projectiles_fired == 0 {
+      return 0.;
+    }
+    self.projectiles_hit as f32 / self.projectiles_fired as f32
+  }
+
+  // Damage taken is not tracked while playing, it is read back from the player's hp history.
+  pub fn with_damage_taken(mut self, hp: &Hp) -> Self {
+    self.damage_taken.clear();
+    for hp_mod in hp.get_modifications().iter().filter(|m| m.amount < 0) {
+      let source = if hp_mod.is_of_source(hp_mod.source_origin) { DamageSource::Contact } else { DamageSource::Projectile };
+      match self.damage_taken.iter_mut().find(|(s, _)| *s == source) {
+        Some((_, amount)) => *amount -= hp_mod.amount,
+        None => self.damage_taken.push((source, -hp_mod.amount)),
+      }
+    }
+    self
+  }
+}
+
+impl Snapshot for KillSource {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    match self {
+      KillSource::Weapon(kind) => {
+        writer.write(0);
+        writer.write(*kind as u8);
+      },
+      KillSource::Bomb => writer.write(1),
+      KillSource::OutOfBounds => writer.write(2),
+      KillSource::Other => writer.write(3),
+    }
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    match reader.read::<u8>()? {
+      0 => Ok(KillSource::Weapon(WeaponKind::from_tag(reader.read()?)?)),
+      1 => Ok(KillSource::Bomb),
+      2 => Ok(KillSource::OutOfBounds),
+      3 => Ok(KillSource::Other),
+      tag => Err(format!("unknown kill source: {}", tag)),
+    }
+  }
+}
+
+impl Snapshot for RunStats {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.time);
+    writer.write(self.kills.len());
+    for (source, count) in &self.kills {
+      writer.write_snapshot(source);
+      writer.write(count);
+    }
+    writer.write(self.projectiles_fired);
+    writer.write(self.projectiles_hit);
+    writer.write(self.distance_walked);
+    writer.write(self.max_difficulty);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let time = reader.read()?;
+    let mut kills = vec![];
+    for _ in 0..reader.read::<usize>()? {
+      kills.push((reader.read_snapshot()?, reader.read()?));
+    }
+    Ok(Self {
+      time,
+      kills,
+      projectiles_fired: reader.read()?,
+      projectiles_hit: reader.read()?,
+      damage_taken: vec![],
+      distance_walked: reader.read()?,
+      max_difficulty: reader.read()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn count_kills_and_damage() {
+    let mut stats = RunStats::default();
+    stats.add_kill(KillSource::Weapon(WeaponKind::Bolt));
+    stats.add_kill(KillSource::Bomb);
+    stats.add_kill(KillSource::Weapon(WeaponKind::Bolt));
+
+    assert_eq!(stats.get_total_kills(), 3);
+    assert_eq!(stats.kills[0], (KillSource::Weapon(WeaponKind::Bolt), 2));
+
+    let mut hp = Hp::new(5);
+    hp.modify(10, 10, -1);
+    hp.modify(11, 12, -2);
+    hp.modify(13, 13, 1);
+    hp.modify(14, 14, -1);
+    let stats = stats.with_damage_taken(&hp);
+
+    assert_eq!(stats.damage_taken, vec![(DamageSource::Contact, 2), (DamageSource::Projectile, 2)]);
+  }
+}
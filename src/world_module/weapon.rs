@@ -0,0 +1,265 @@
+use std::f32::consts::PI;
+
+use macroquad::prelude::*;
+
+use crate::{systems::timer::Timer, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
+
+use super::{actor::Actor, projectile::{Projectile, spawn_projectile_in_direction}};
+
+const SPREAD_ANGLE: f32 = 0.5;
+const ORBIT_RADIUS: f32 = 56.;
+const ORBIT_ANGULAR_SPEED: f32 = 3.;
+const ORBIT_LIFETIME: f32 = 3.;
+const NOVA_LIFETIME: f32 = 0.6;
+const BEAM_SPEED: f32 = 450.;
+const BEAM_PIERCE: usize = 5;
+const BEAM_LIFETIME: f32 = 1.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeaponKind {
+  Bolt,
+  Spread,
+  Orbit,
+  Nova,
+  Beam,
+}
+
+impl WeaponKind {
+  pub fn get_name(&self) -> &'static str {
+    match self {
+      WeaponKind::Bolt => "bolt",
+      WeaponKind::Spread => "spread",
+      WeaponKind::Orbit => "orbit",
+      WeaponKind::Nova => "nova",
+      WeaponKind::Beam => "beam",
+    }
+  }
+
+  pub fn from_tag(tag: u8) -> Result<Self, String> {
+    match tag {
+      0 => Ok(WeaponKind::Bolt),
+      1 => Ok(WeaponKind::Spread),
+      2 => Ok(WeaponKind::Orbit),
+      3 => Ok(WeaponKind::Nova),
+      4 => Ok(WeaponKind::Beam),
+      tag => Err(format!("unknown weapon kind: {}", tag)),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Targeting {
+  Closest,
+  Toughest,
+  Around,
+}
+
+#[derive(Debug, Clone)]
+pub struct Weapon {
+  pub kind: WeaponKind,
+  pub cooldown: Timer,
+  pub damage: isize,
+  pub projectile_count: usize,
+  pub targeting: Targeting,
+  pub level: usize,
+}
+
+impl Weapon {
+  pub fn new(kind: WeaponKind) -> Self {
+    let (cooldown, damage, projectile_count, targeting) = match kind {
+      WeaponKind::Bolt => (1., 1, 1, Targeting::Closest),
+      WeaponKind::Spread => (1.6, 1, 3, Targeting::Closest),
+      WeaponKind::Orbit => (4., 1, 2, Targeting::Around),
+      WeaponKind::Nova => (3., 1, 12, Targeting::Around),
+      WeaponKind::Beam => (2.5, 2, 1, Targeting::Toughest),
+    };
+
+    Self {
+      kind,
+      cooldown: Timer::new_timeout(cooldown),
+      damage,
+      projectile_count,
+      targeting,
+      level: 1,
+    }
+  }
+
+  pub fn level_up(&mut self) {
+    self.level += 1;
+    match self.kind {
+      WeaponKind::Bolt | WeaponKind::Beam => self.damage += 1,
+      WeaponKind::Spread | WeaponKind::Orbit => self.projectile_count += 1,
+      WeaponKind::Nova => self.projectile_count += 4,
+    }
+  }
+
+  fn find_target<'a>(&self, position: &Vec2, enemies: &'a [Actor]) -> Option<&'a Actor> {
+    let distance = |e: &Actor| position.distance_squared(e.movable.position);
+    match self.targeting {
+      Targeting::Closest => enemies.iter().min_by(|a, b| distance(a).total_cmp(&distance(b))),
+      Targeting::Toughest => enemies.iter().min_by(|a, b| b.hp.act_hp.cmp(&a.hp.act_hp).then(distance(a).total_cmp(&distance(b)))),
+      Targeting::Around => None,
+    }
+  }
+
+  fn fire(&self, origin_id: usize, position: &Vec2, direction: Vec2) -> Vec<Projectile> {
+    let count = self.projectile_count.max(1);
+    let rotation = direction.y.atan2(direction.x);
+    let directions: Vec<Vec2> = match self.kind {
+      WeaponKind::Bolt | WeaponKind::Beam => vec![direction; count],
+      WeaponKind::Spread => (0..count)
+        .map(|i| {
+          let offset = if count > 1 { SPREAD_ANGLE * (i as f32 / (count - 1) as f32 - 0.5) } else { 0. };
+          Vec2::new((rotation + offset).cos(), (rotation + offset).sin())
+        })
+        .collect(),
+      WeaponKind::Orbit | WeaponKind::Nova => (0..count)
+        .map(|i| {
+          let angle = PI * 2. * i as f32 / count as f32;
+          Vec2::new(angle.cos(), angle.sin())
+        })
+        .collect(),
+    };
+
+    directions
+      .into_iter()
+      .enumerate()
+      .map(|(i, direction)| {
+        let projectile = spawn_projectile_in_direction(origin_id, position, direction)
+          .with_damage(self.damage)
+          .with_weapon(self.kind);
+        match self.kind {
+          WeaponKind::Bolt | WeaponKind::Spread => projectile,
+          WeaponKind::Orbit => projectile
+            .with_orbit(ORBIT_RADIUS, PI * 2. * i as f32 / count as f32, ORBIT_ANGULAR_SPEED)
+            .with_pierce(usize::MAX)
+            .with_lifetime(ORBIT_LIFETIME),
+          WeaponKind::Nova => projectile.with_pierce(1).with_lifetime(NOVA_LIFETIME),
+          WeaponKind::Beam => projectile
+            .with_speed(BEAM_SPEED)
+            .with_size(20., 4.)
+            .with_pierce(BEAM_PIERCE)
+            .with_lifetime(BEAM_LIFETIME),
+        }
+      })
+      .collect()
+  }
+
+  pub fn update(&mut self, delta_t: f32, fire_rate: f32, owner: &Actor, enemies: &[Actor], projectiles: &mut Vec<Projectile>) {
+    self.cooldown.update(delta_t * fire_rate);
+    if !self.cooldown.is_over() || enemies.is_empty() {
+      return;
+    }
+
+    let position = &owner.movable.position;
+    let direction = match self.targeting {
+      Targeting::Around => Vec2::X,
+      _ => match self.find_target(position, enemies) {
+        Some(target) => (target.movable.position - *position).normalize_or_zero(),
+        None => return,
+      },
+    };
+    if direction == Vec2::ZERO {
+      return;
+    }
+
+    projectiles.extend(self.fire(owner.get_id(), position, direction));
+    self.cooldown.reset();
+  }
+}
+
+impl Snapshot for Weapon {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write(self.kind as u8);
+    writer.write_snapshot(&self.cooldown);
+    writer.write(self.damage);
+    writer.write(self.projectile_count);
+    writer.write(self.targeting as u8);
+    writer.write(self.level);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    Ok(Self {
+      kind: WeaponKind::from_tag(reader.read()?)?,
+      cooldown: reader.read_snapshot()?,
+      damage: reader.read()?,
+      projectile_count: reader.read()?,
+      targeting: match reader.read::<u8>()? {
+        0 => Targeting::Closest,
+        1 => Targeting::Toughest,
+        2 => Targeting::Around,
+        tag => return Err(format!("unknown weapon targeting: {}", tag)),
+      },
+      level: reader.read()?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn frames_to_fire(kind: WeaponKind, fire_rate: f32, enemies: &[Actor]) -> (usize, Vec<Projectile>) {
+    let owner = Actor::new(Vec2::ZERO, 100., 5);
+    let mut weapon = Weapon::new(kind);
+    let mut projectiles = vec![];
+    for frame in 0..600 {
+      weapon.update(1. / 60., fire_rate, &owner, enemies, &mut projectiles);
+      if !projectiles.is_empty() {
+        return (frame, projectiles);
+      }
+    }
+    (600, projectiles)
+  }
+
+  fn fire_once(kind: WeaponKind, enemies: &[Actor]) -> Vec<Projectile> {
+    frames_to_fire(kind, 1., enemies).1
+  }
+
+  #[test]
+  fn weapons_fire_their_projectiles() {
+    let enemies = vec![Actor::new(Vec2::new(100., 0.), 50., 1), Actor::new(Vec2::new(0., -200.), 50., 4)];
+
+    let bolt = fire_once(WeaponKind::Bolt, &enemies);
+    assert_eq!(bolt.len(), 1);
+    assert!(bolt[0].movable.velocity.x > 0.);
+
+    let beam = fire_once(WeaponKind::Beam, &enemies);
+    assert_eq!(beam.len(), 1);
+    assert!(beam[0].movable.velocity.y < 0.);
+    assert_eq!(beam[0].pierce, BEAM_PIERCE);
+
+    assert_eq!(fire_once(WeaponKind::Spread, &enemies).len(), 3);
+    assert_eq!(fire_once(WeaponKind::Nova, &enemies).len(), 12);
+    assert_eq!(fire_once(WeaponKind::Orbit, &enemies).len(), 2);
+    assert!(fire_once(WeaponKind::Bolt, &[]).is_empty());
+  }
+
+  #[test]
+  fn fire_rate_shortens_cooldown() {
+    let enemies = vec![Actor::new(Vec2::new(100., 0.), 50., 1)];
+    let (frames, _) = frames_to_fire(WeaponKind::Nova, 1., &enemies);
+    let (faster_frames, _) = frames_to_fire(WeaponKind::Nova, 2., &enemies);
+
+    assert_eq!(faster_frames, frames / 2);
+  }
+
+  #[test]
+  fn orbit_follows_anchor_and_pierces() {
+    let enemies = vec![Actor::new(Vec2::new(100., 0.), 50., 1)];
+    let mut blade = fire_once(WeaponKind::Orbit, &enemies).remove(0);
+    let anchor = Vec2::new(10., 10.);
+
+    blade.update(0.1, &anchor);
+    assert!((blade.movable.position.distance(anchor) - ORBIT_RADIUS).abs() < 0.01);
+
+    let mut target = Actor::new(blade.movable.position, 50., 3);
+    blade.apply(&mut target);
+    blade.apply(&mut target);
+    assert_eq!(target.hp.act_hp, 2);
+    assert!(blade.is_alive);
+
+    blade.update(ORBIT_LIFETIME, &anchor);
+    assert!(!blade.is_alive);
+  }
+}
@@ -3,35 +3,65 @@ use std::collections::HashMap;
 use macroquad::{prelude::*};
 
 
-use crate::{player::Player, systems::ai::Ai};
+use crate::{player::Player, systems::{ai::{Ai, AiState}, atlas::get_atlas, rng::Rng, cd::{SpatialGrid, CdBounds, Contact}}, snapshot::{Snapshot, SnapshotWriter, SnapshotReader}};
 
-use super::{projectile::Projectile, actor::Actor, particle::{ParticleSystem, Particle}};
+use super::{projectile::Projectile, actor::Actor, particle::{ParticleSystem, Particle}, pickup::{Pickup, PickupKind, DropTable}, stats::{RunStats, KillSource}, damage::HpModification};
 
 pub const WORLD_WIDTH: f32 = 2000.;
 pub const WORLD_HEIGHT: f32 = 2000.;
 
 pub const OUT_OF_BOUNDS_SRC: usize = 0;
 
+const GRID_CELL_SIZE: f32 = 64.;
+const DEATH_PARTICLES: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntityRef {
+  Player,
+  Actor(usize),
+  Projectile(usize),
+  Pickup(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+  pub a: EntityRef,
+  pub b: EntityRef,
+  pub contact: Contact,
+}
+
 pub struct World {
   pub player: Player,
   ai_actors: Vec<Actor>,
   ai_controllers: HashMap<usize, Ai>,
+  dying_actors: Vec<Actor>,
   projectiles: Vec<Projectile>,
+  pickups: Vec<Pickup>,
+  pub drops: DropTable,
   pub particle_system: ParticleSystem,
   pub bounds: Rect,
   pub score: usize,
+  pub stats: RunStats,
+  pub rng: Rng,
+  collision_grid: SpatialGrid,
 }
 
 impl World {
-  pub fn new(player: Player) -> Self {
+  pub fn new(player: Player, rng: Rng) -> Self {
     Self {
       player,
       ai_actors: vec![],
       ai_controllers: HashMap::new(),
+      dying_actors: vec![],
       projectiles: vec![],
+      pickups: vec![],
+      drops: DropTable::default(),
       particle_system: ParticleSystem::new(),
       bounds: Rect::new(-WORLD_WIDTH / 2., -WORLD_HEIGHT / 2., WORLD_WIDTH, WORLD_HEIGHT),
-      score: 0
+      score: 0,
+      stats: RunStats::default(),
+      rng,
+      collision_grid: SpatialGrid::new(GRID_CELL_SIZE),
     }
   }
 
@@ -49,10 +79,39 @@ impl World {
     self.ai_actors.push(actor);
   }
 
+  pub fn has_ai_actor(&self, id: usize) -> bool {
+    self.ai_controllers.contains_key(&id)
+  }
+
   pub fn on_mouse_button_down(&mut self, position: Vec2) {
+    let position = self.clamp_to_bounds(position, self.get_player_margin());
     self.player.actor.move_to(position);
   }
 
+  pub fn steer_player(&mut self, direction: Vec2) {
+    self.player.actor.steer(direction);
+  }
+
+  // bounds inset by margin, it should be positive as Rect::contains excludes the right and bottom edges
+  pub fn clamp_to_bounds(&self, position: Vec2, margin: Vec2) -> Vec2 {
+    let min = self.bounds.point() + margin;
+    let max = (self.bounds.point() + self.bounds.size() - margin).max(min);
+    position.clamp(min, max)
+  }
+
+  fn get_player_margin(&self) -> Vec2 {
+    self.player.actor.cd_bounds.get_rect().size() / 2.
+  }
+
+  // steering has no target position to check, so the player is pushed back inside after moving
+  fn keep_player_inside(&mut self) {
+    let position = self.clamp_to_bounds(self.player.actor.movable.position, self.get_player_margin());
+    if position != self.player.actor.movable.position {
+      self.player.actor.movable.position = position;
+      self.player.actor.cd_bounds.update_position(&position);
+    }
+  }
+
   pub fn get_player(&self) -> &Player {
     &self.player
   }
@@ -61,83 +120,267 @@ impl World {
     &self.ai_actors
   }
 
+  pub fn get_dying_actors(&self) -> &Vec<Actor> {
+    &self.dying_actors
+  }
+
   pub fn get_projectiles(&self) -> &Vec<Projectile> {
     &self.projectiles
   }
 
+  pub fn get_pickups(&self) -> &Vec<Pickup> {
+    &self.pickups
+  }
+
   pub fn get_particles(&self) -> &Vec<Particle> {
     &self.particle_system.particles
   }
 
+  pub fn get_run_stats(&self) -> RunStats {
+    self.stats.clone().with_damage_taken(&self.player.actor.hp)
+  }
+
+  fn get_kill_source(&self, hp_mod: &HpModification) -> KillSource {
+    if hp_mod.is_of_source(OUT_OF_BOUNDS_SRC) {
+      return KillSource::OutOfBounds;
+    }
+    if let Some(projectile) = self.projectiles.iter().find(|p| hp_mod.is_of_source(p.get_id())) {
+      return projectile.weapon.map(KillSource::Weapon).unwrap_or(KillSource::Other);
+    }
+    if self.pickups.iter().any(|p| p.kind == PickupKind::Bomb && hp_mod.is_of_source(p.get_id())) {
+      return KillSource::Bomb;
+    }
+    KillSource::Other
+  }
+
   fn cleanup(&mut self) {
-    self.projectiles = self.projectiles.clone().into_iter().filter(|p| p.is_alive).collect();
-    let (alive, dead) = self.ai_actors
-      .clone()
-      .into_iter()
+    let (alive, dead): (Vec<Actor>, Vec<Actor>) = self.ai_actors
+      .drain(..)
       .partition(|a| a.is_alive());
 
     self.ai_actors = alive;
-    for actor in dead {
+    for mut actor in dead {
       self.ai_controllers.remove(&actor.get_id());
       if let Some(hp_mod) = actor.hp.get_last_modification() {
+        let source = self.get_kill_source(&hp_mod);
+        self.stats.add_kill(source);
         if hp_mod.source_origin == self.player.actor.get_id() {
-          self.score += 1;
+          self.score += actor.score_value;
+          let drops = self.drops.roll(actor.movable.position, actor.xp_value, &mut self.rng);
+          self.pickups.extend(drops);
+        }
+      }
+      self.particle_system.add_burst(actor.movable.position, DEATH_PARTICLES, "death_particle", 0.4);
+      actor.stop();
+      self.dying_actors.push(actor);
+    }
+    self.dying_actors.retain(|a| !a.has_finished_dying());
+
+    self.projectiles.retain(|p| p.is_alive);
+    self.pickups.retain(|p| p.is_alive);
+  }
+
+  fn get_cd_bounds(&self, entity: EntityRef) -> &CdBounds {
+    match entity {
+      EntityRef::Player => &self.player.actor.cd_bounds,
+      EntityRef::Actor(index) => &self.ai_actors[index].cd_bounds,
+      EntityRef::Projectile(index) => &self.projectiles[index].cd_bounds,
+      EntityRef::Pickup(index) => &self.pickups[index].cd_bounds,
+    }
+  }
+
+  fn collect_pickup(&mut self, index: usize) {
+    let pickup = &mut self.pickups[index];
+    pickup.is_alive = false;
+    let (id, kind, value) = (pickup.get_id(), pickup.kind, pickup.value);
+
+    match kind {
+      PickupKind::Health => self.player.heal(id, value as isize),
+      PickupKind::Experience => self.player.add_xp(value),
+      PickupKind::Magnet => {
+        for gem in self.pickups.iter_mut().filter(|p| p.kind == PickupKind::Experience) {
+          gem.attracted = true;
+        }
+      },
+      PickupKind::Bomb => {
+        let player_id = self.player.actor.get_id();
+        let position = self.player.actor.movable.position;
+        let radius_squared = self.drops.bomb_radius * self.drops.bomb_radius;
+        for actor in self.ai_actors.iter_mut().filter(|a| a.movable.position.distance_squared(position) < radius_squared) {
+          actor.hp.modify(id, player_id, -actor.hp.act_hp);
+        }
+        for projectile in self.projectiles.iter_mut().filter(|p| p.origin_id != player_id && p.movable.position.distance_squared(position) < radius_squared) {
+          projectile.is_alive = false;
+        }
+      },
+    }
+  }
+
+  fn detect_collisions(&mut self) -> Vec<CollisionEvent> {
+    let mut entities = vec![EntityRef::Player];
+    entities.extend((0..self.ai_actors.len()).map(EntityRef::Actor));
+    entities.extend((0..self.projectiles.len()).filter(|i| self.projectiles[*i].is_alive).map(EntityRef::Projectile));
+    entities.extend((0..self.pickups.len()).filter(|i| self.pickups[*i].is_alive).map(EntityRef::Pickup));
+
+    let rects: Vec<Rect> = entities.iter().map(|e| self.get_cd_bounds(*e).get_rect()).collect();
+    self.collision_grid.clear();
+    for (index, rect) in rects.iter().enumerate() {
+      self.collision_grid.insert(index, rect);
+    }
+
+    let mut events = vec![];
+    let mut candidates: Vec<usize> = vec![];
+    for (index, entity) in entities.iter().enumerate() {
+      let bounds = self.get_cd_bounds(*entity);
+      self.collision_grid.query(&rects[index], &mut candidates);
+      for other_index in candidates.iter().filter(|i| **i > index) {
+        let other = entities[*other_index];
+        let other_bounds = self.get_cd_bounds(other);
+        if !bounds.can_collide_with(other_bounds) {
+          continue;
+        }
+        if let Some(contact) = bounds.contact(other_bounds) {
+          events.push(CollisionEvent { a: *entity, b: other, contact });
         }
       }
     }
+    events
+  }
+
+  fn resolve_collision(&mut self, event: &CollisionEvent, impulses: &mut [Option<Vec2>]) {
+    match (event.a, event.b) {
+      (EntityRef::Player, EntityRef::Actor(index)) => {
+        let actor = &mut self.ai_actors[index];
+        if let Some(ai) = self.ai_controllers.get_mut(&actor.get_id()) {
+          ai.set_state(AiState::Wandering, actor, &self.player.actor, &self.bounds, &mut self.rng);
+          self.player.modify_hp(actor.get_id(), actor.get_id(), -1);
+        }
+      },
+      (EntityRef::Player, EntityRef::Projectile(projectile_index)) => {
+        let projectile = &mut self.projectiles[projectile_index];
+        if projectile.is_alive && !self.player.invlunerable {
+          self.player.modify_hp(projectile.get_id(), projectile.origin_id, -1);
+          projectile.is_alive = false;
+        }
+      },
+      (EntityRef::Player, EntityRef::Pickup(index)) if self.pickups[index].is_alive => self.collect_pickup(index),
+      (EntityRef::Actor(index_a), EntityRef::Actor(index_b)) => {
+        impulses[index_a] = Some(event.contact.normal * 120.);
+        impulses[index_b] = Some(-event.contact.normal * 120.);
+      },
+      (EntityRef::Actor(index), EntityRef::Projectile(projectile_index)) => {
+        let projectile = &mut self.projectiles[projectile_index];
+        if projectile.is_alive && projectile.apply(&mut self.ai_actors[index]) && !projectile.has_hit {
+          projectile.has_hit = true;
+          self.stats.projectiles_hit += 1;
+        }
+      },
+      _ => (),
+    }
   }
 
   pub fn update(&mut self, delta_t: f32) {
+    let projectiles_count = self.projectiles.len();
+    let player_position = self.player.actor.movable.position;
     self.player.update(delta_t, &mut self.projectiles, &self.ai_actors);
     self.player.actor.update(delta_t);
+    self.keep_player_inside();
+    self.stats.time += delta_t;
+    self.stats.projectiles_fired += self.projectiles.len() - projectiles_count;
+    self.stats.distance_walked += self.player.actor.movable.position.distance(player_position);
 
     self.particle_system.update(delta_t);
 
     for projectile in &mut self.projectiles {
-      projectile.update(delta_t);
+      projectile.update(delta_t, &self.player.actor.movable.position);
       if !self.bounds.contains(projectile.movable.position) {
         projectile.is_alive = false;
         continue;
       }
       if projectile.particles_timer.is_just_over() {
-        let frames = vec![
-          Rect::new(16., 0., 16., 16.),
-          Rect::new(32., 0., 16., 16.),
-          Rect::new(48., 0., 16., 16.),
-        ];
-        self.particle_system.add_particle(Particle::new(projectile.movable.position, Vec2::ZERO, frames, 0.3))
-      }
-      if let Some(collided_actor) = self.ai_actors.iter_mut().find(|actor| actor.cd_bounds.collide_with(&projectile.cd_bounds)) {
-        projectile.apply(collided_actor);
+        let animation = get_atlas().get_animation("fire_particle", false);
+        self.particle_system.add_particle(Particle::new(projectile.movable.position, Vec2::ZERO, animation, 0.3))
       }
     }
 
-    let actors_clone = self.ai_actors.clone();
-    for actor_a in self.ai_actors.iter_mut() {
-      if !self.bounds.contains(actor_a.movable.position) {
-        actor_a.hp.modify(OUT_OF_BOUNDS_SRC, OUT_OF_BOUNDS_SRC, -50);
+    for actor in self.ai_actors.iter_mut() {
+      if !self.bounds.contains(actor.movable.position) {
+        actor.hp.modify(OUT_OF_BOUNDS_SRC, OUT_OF_BOUNDS_SRC, -50);
         continue;
       }
-      if let Some(ai) = self.ai_controllers.get_mut(&actor_a.get_id()) {
-        ai.update(delta_t, actor_a, &self.player.actor);
-
-        if actor_a.cd_bounds.collide_with(&self.player.actor.cd_bounds) {
-          ai.set_state(crate::systems::ai::AiState::Wandering, actor_a, &self.player.actor);
-          self.player.modify_hp(actor_a.get_id(), -1);
-        }
-      }
-      actor_a.update(delta_t);
-      let mut impuls: Option<Vec2> = None;
-      for actor_b in &actors_clone {
-        if actor_a.get_id() != actor_b.get_id() && actor_a.cd_bounds.collide_with(&actor_b.cd_bounds) {
-          impuls = Some((actor_a.movable.position - actor_b.movable.position).normalize() * 120.);
+      if let Some(ai) = self.ai_controllers.get_mut(&actor.get_id()) {
+        if let Some(projectile) = ai.update(delta_t, actor, &self.player.actor, &self.bounds, &mut self.rng) {
+          self.projectiles.push(projectile);
         }
       }
+      actor.update(delta_t);
+    }
+    for actor in self.dying_actors.iter_mut() {
+      actor.update(delta_t);
+    }
+
+    for pickup in &mut self.pickups {
+      pickup.update(delta_t, &self.player.actor.movable.position, &self.drops);
+    }
+
+    let mut impulses: Vec<Option<Vec2>> = vec![None; self.ai_actors.len()];
+    for event in self.detect_collisions() {
+      self.resolve_collision(&event, &mut impulses);
+    }
+    for (actor, impuls) in self.ai_actors.iter_mut().zip(impulses) {
       if let Some(imp) = impuls {
-        actor_a.movable.add_impuls(imp);
+        actor.movable.add_impuls(imp);
       }
     }
 
     self.cleanup();
   }
-}
\ No newline at end of file
+}
+
+impl Snapshot for World {
+  fn save(&self, writer: &mut SnapshotWriter) {
+    writer.write_snapshot(&self.player);
+    writer.write_list(&self.ai_actors);
+    let mut controller_ids: Vec<&usize> = self.ai_controllers.keys().collect();
+    controller_ids.sort();
+    writer.write(controller_ids.len());
+    for id in controller_ids {
+      writer.write(id);
+      writer.write_snapshot(&self.ai_controllers[id]);
+    }
+    writer.write_list(&self.dying_actors);
+    writer.write_list(&self.projectiles);
+    writer.write_list(&self.pickups);
+    writer.write_snapshot(&self.particle_system);
+    writer.write_rect(&self.bounds);
+    writer.write(self.score);
+    writer.write_snapshot(&self.stats);
+    writer.write_snapshot(&self.rng);
+  }
+
+  fn load(reader: &mut SnapshotReader) -> Result<Self, String> {
+    let player = reader.read_snapshot()?;
+    let ai_actors = reader.read_list()?;
+    let controllers_len = reader.read::<usize>()?;
+    let mut ai_controllers = HashMap::new();
+    for _ in 0..controllers_len {
+      let id = reader.read::<usize>()?;
+      ai_controllers.insert(id, reader.read_snapshot()?);
+    }
+    Ok(Self {
+      player,
+      ai_actors,
+      ai_controllers,
+      dying_actors: reader.read_list()?,
+      projectiles: reader.read_list()?,
+      pickups: reader.read_list()?,
+      drops: DropTable::default(),
+      particle_system: reader.read_snapshot()?,
+      bounds: reader.read_rect()?,
+      score: reader.read()?,
+      stats: reader.read_snapshot()?,
+      rng: reader.read_snapshot()?,
+      collision_grid: SpatialGrid::new(GRID_CELL_SIZE),
+    })
+  }
+}